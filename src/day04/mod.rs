@@ -1,7 +1,93 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
+    use std::fmt;
+    use std::fmt::Display;
     use std::str::FromStr;
 
+    use proptest::prelude::*;
+
+    #[derive(Debug, PartialEq)]
+    enum ParseRangeError {
+        MissingDash(String),
+        InvalidBound(String, String),
+        SectionTooLarge(String, String),
+        Reversed(String),
+    }
+
+    impl Display for ParseRangeError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParseRangeError::MissingDash(range) => {
+                    write!(f, "The range {:?} is invalid. Expected two section numbers separated with a dash", range)
+                }
+                ParseRangeError::InvalidBound(bound, range) => {
+                    write!(f, "Invalid section number {:?} in range {:?}", bound, range)
+                }
+                ParseRangeError::SectionTooLarge(bound, range) => write!(
+                    f,
+                    "Section {} in range {:?} is above the largest section {}",
+                    bound, range, MAX_SECTION
+                ),
+                ParseRangeError::Reversed(range) => {
+                    write!(f, "The range {:?} starts after it ends", range)
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum ParsePairError {
+        InvalidRangeCount(usize, String),
+        InvalidRange(ParseRangeError),
+    }
+
+    impl Display for ParsePairError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParsePairError::InvalidRangeCount(count, pair) => {
+                    write!(
+                        f,
+                        "The pair {:?} has {} ranges, expected 2 separated with a comma",
+                        pair, count
+                    )
+                }
+                ParsePairError::InvalidRange(err) => write!(f, "{}", err),
+            }
+        }
+    }
+
+    impl From<ParseRangeError> for ParsePairError {
+        fn from(err: ParseRangeError) -> Self {
+            ParsePairError::InvalidRange(err)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ParseError<E> {
+        line: usize,
+        error: E,
+    }
+
+    impl<E: Display> Display for ParseError<E> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Line {}: {}", self.line, self.error)
+        }
+    }
+
+    fn parse_lines<T: FromStr>(input: &str) -> Result<Vec<T>, ParseError<T::Err>> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| T::from_str(line).map_err(|error| ParseError { line: i + 1, error }))
+            .collect()
+    }
+
+    // One below `u64::MAX`, so the section right after any range and the length of any
+    // range both still fit in a `u64`.
+    const MAX_SECTION: u64 = u64::MAX - 1;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
     struct Range {
         from: u64,
         to: u64,
@@ -15,83 +101,666 @@ mod tests {
         fn overlaps(&self, other: &Self) -> bool {
             self.to >= other.from && other.to >= self.from
         }
+
+        fn len(&self) -> u64 {
+            self.to - self.from + 1
+        }
+
+        fn intersection(&self, other: &Self) -> Option<Range> {
+            if !self.overlaps(other) {
+                return None;
+            }
+            Some(Range {
+                from: self.from.max(other.from),
+                to: self.to.min(other.to),
+            })
+        }
     }
 
     impl FromStr for Range {
-        type Err = ();
+        type Err = ParseRangeError;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let parts: Vec<&str> = s.split('-').collect();
-            if parts.len() != 2 {
-                return Err(());
+            let (from, to) = s
+                .split_once('-')
+                .ok_or_else(|| ParseRangeError::MissingDash(s.to_owned()))?;
+            let from = u64::from_str(from)
+                .map_err(|_| ParseRangeError::InvalidBound(from.to_owned(), s.to_owned()))?;
+            let to = u64::from_str(to)
+                .map_err(|_| ParseRangeError::InvalidBound(to.to_owned(), s.to_owned()))?;
+            if to > MAX_SECTION {
+                return Err(ParseRangeError::SectionTooLarge(
+                    to.to_string(),
+                    s.to_owned(),
+                ));
+            }
+            if from > to {
+                return Err(ParseRangeError::Reversed(s.to_owned()));
             }
-            let from = u64::from_str(parts[0]).map_err(|_| ())?;
-            let to = u64::from_str(parts[1]).map_err(|_| ())?;
             Ok(Range { from, to })
         }
     }
 
+    #[derive(Debug, PartialEq)]
     struct Pair(Range, Range);
 
+    #[derive(Debug, PartialEq)]
+    enum Containment {
+        Equal,
+        FirstContainsSecond,
+        SecondContainsFirst,
+        Neither,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Reassignment {
+        Keep,
+        Unassign(usize),
+        Trim(usize, Range),
+    }
+
     impl Pair {
         fn fully_contains(&self) -> bool {
             self.0.fully_contains(&self.1) || self.1.fully_contains(&self.0)
         }
+
         fn overlaps(&self) -> bool {
             self.0.overlaps(&self.1)
         }
+
+        fn containment(&self) -> Containment {
+            match (
+                self.0.fully_contains(&self.1),
+                self.1.fully_contains(&self.0),
+            ) {
+                (true, true) => Containment::Equal,
+                (true, false) => Containment::FirstContainsSecond,
+                (false, true) => Containment::SecondContainsFirst,
+                (false, false) => Containment::Neither,
+            }
+        }
+
+        fn overlap_size(&self) -> u64 {
+            self.0.intersection(&self.1).map_or(0, |r| r.len())
+        }
+
+        // Elves are numbered by their position in the pair. A contained elf is redundant
+        // and can be unassigned, while on a partial overlap the second elf gives up the
+        // shared sections.
+        fn reassignment(&self) -> Reassignment {
+            match self.containment() {
+                Containment::Equal | Containment::FirstContainsSecond => Reassignment::Unassign(1),
+                Containment::SecondContainsFirst => Reassignment::Unassign(0),
+                Containment::Neither if !self.overlaps() => Reassignment::Keep,
+                Containment::Neither if self.1.from < self.0.from => Reassignment::Trim(
+                    1,
+                    Range {
+                        from: self.1.from,
+                        to: self.0.from - 1,
+                    },
+                ),
+                Containment::Neither => Reassignment::Trim(
+                    1,
+                    Range {
+                        from: self.0.to + 1,
+                        to: self.1.to,
+                    },
+                ),
+            }
+        }
+
+        fn swapped(&self) -> Pair {
+            Pair(self.1, self.0)
+        }
     }
 
     impl FromStr for Pair {
-        type Err = ();
+        type Err = ParsePairError;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
             let parts: Vec<&str> = s.split(',').collect();
             if parts.len() != 2 {
-                return Err(());
+                return Err(ParsePairError::InvalidRangeCount(parts.len(), s.to_owned()));
             }
-            let p1 = Range::from_str(parts[0]).map_err(|_| ())?;
-            let p2 = Range::from_str(parts[1]).map_err(|_| ())?;
-            Ok(Pair(p2, p1))
+            let p1 = Range::from_str(parts[0])?;
+            let p2 = Range::from_str(parts[1])?;
+            Ok(Pair(p1, p2))
         }
     }
 
-    fn solution_part1(input: &str) -> usize {
-        let rules: Result<Vec<Pair>, <Pair as FromStr>::Err> =
-            input.lines().map(Pair::from_str).collect();
-        match rules {
-            Ok(rows) => rows.iter().filter(|pair| pair.fully_contains()).count(),
-            Err(()) => panic!("Error occurred"),
+    #[derive(Debug)]
+    struct Group(Vec<Range>);
+
+    impl FromStr for Group {
+        type Err = ParseRangeError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let ranges: Result<Vec<Range>, ParseRangeError> =
+                s.split(',').map(Range::from_str).collect();
+            Ok(Group(ranges?))
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    struct ElfId {
+        group: usize,
+        elf: usize,
+    }
+
+    struct Segment {
+        range: Range,
+        elves: BTreeSet<ElfId>,
+    }
+
+    struct Coverage {
+        segments: Vec<Segment>,
+    }
+
+    impl Coverage {
+        // Sweep over section boundaries: an assignment starts covering at `from` and stops
+        // right after `to`, so each pair of consecutive boundaries forms a segment of
+        // sections covered by exactly the same set of elves.
+        fn new(groups: &[Group]) -> Self {
+            let mut events: Vec<(u64, bool, ElfId)> = Vec::new();
+            for (group, Group(ranges)) in groups.iter().enumerate() {
+                for (elf, range) in ranges.iter().enumerate() {
+                    let id = ElfId { group, elf };
+                    events.push((range.from, true, id));
+                    events.push((range.to + 1, false, id));
+                }
+            }
+            events.sort_by_key(|(section, _, _)| *section);
+
+            let mut segments = Vec::new();
+            let mut elves = BTreeSet::new();
+            let mut i = 0;
+            while i < events.len() {
+                let section = events[i].0;
+                while i < events.len() && events[i].0 == section {
+                    let (_, starts, id) = events[i];
+                    if starts {
+                        elves.insert(id);
+                    } else {
+                        elves.remove(&id);
+                    }
+                    i += 1;
+                }
+                if i < events.len() {
+                    segments.push(Segment {
+                        range: Range {
+                            from: section,
+                            to: events[i].0 - 1,
+                        },
+                        elves: elves.clone(),
+                    });
+                }
+            }
+
+            Coverage { segments }
+        }
+
+        fn ranges_where(&self, predicate: impl Fn(&Segment) -> bool) -> Vec<Range> {
+            let mut ranges: Vec<Range> = Vec::new();
+            for segment in self.segments.iter().filter(|s| predicate(s)) {
+                match ranges.last_mut() {
+                    Some(last) if last.to + 1 == segment.range.from => last.to = segment.range.to,
+                    _ => ranges.push(segment.range),
+                }
+            }
+            ranges
+        }
+
+        fn covered_by_more_than(&self, count: usize) -> Vec<Range> {
+            self.ranges_where(|segment| segment.elves.len() > count)
+        }
+
+        // Gaps only exist between the first and the last assigned section; nothing before
+        // or after every assignment is reported.
+        fn uncovered(&self) -> Vec<Range> {
+            self.ranges_where(|segment| segment.elves.is_empty())
+        }
+
+        // Every segment covered by the most elves, in section order. Neighbouring segments
+        // stay apart since they are covered by different elves.
+        fn max_depth(&self) -> Vec<(Range, &BTreeSet<ElfId>)> {
+            let depth = self
+                .segments
+                .iter()
+                .map(|segment| segment.elves.len())
+                .max();
+            self.segments
+                .iter()
+                .filter(|segment| Some(segment.elves.len()) == depth)
+                .map(|segment| (segment.range, &segment.elves))
+                .collect()
         }
     }
 
-    fn solution_part2(input: &str) -> usize {
-        let rules: Result<Vec<Pair>, <Pair as FromStr>::Err> =
-            input.lines().map(Pair::from_str).collect();
-        match rules {
-            Ok(rows) => rows.iter().filter(|pair| pair.overlaps()).count(),
-            Err(()) => panic!("Error occurred"),
+    // Assignments are sorted by their first section and laid out as an implicit balanced
+    // tree: the root of any slice is its middle element, and `max_to` keeps the last
+    // section covered by the subtree rooted there, which lets queries skip whole subtrees.
+    struct SectionIndex {
+        entries: Vec<(Range, ElfId)>,
+        max_to: Vec<u64>,
+    }
+
+    impl SectionIndex {
+        fn new(groups: &[Group]) -> Self {
+            let mut entries: Vec<(Range, ElfId)> = groups
+                .iter()
+                .enumerate()
+                .flat_map(|(group, Group(ranges))| {
+                    ranges
+                        .iter()
+                        .enumerate()
+                        .map(move |(elf, range)| (*range, ElfId { group, elf }))
+                })
+                .collect();
+            entries.sort_by_key(|(range, id)| (range.from, *id));
+
+            let mut index = SectionIndex {
+                max_to: vec![0; entries.len()],
+                entries,
+            };
+            index.build(0, index.entries.len());
+            index
+        }
+
+        fn build(&mut self, lo: usize, hi: usize) -> u64 {
+            if lo >= hi {
+                return 0;
+            }
+            let mid = (lo + hi) / 2;
+            let max_to = self.entries[mid]
+                .0
+                .to
+                .max(self.build(lo, mid))
+                .max(self.build(mid + 1, hi));
+            self.max_to[mid] = max_to;
+            max_to
+        }
+
+        fn collect(&self, lo: usize, hi: usize, range: &Range, found: &mut Vec<ElfId>) {
+            if lo >= hi {
+                return;
+            }
+            let mid = (lo + hi) / 2;
+            if self.max_to[mid] < range.from {
+                return;
+            }
+            self.collect(lo, mid, range, found);
+            let (candidate, id) = &self.entries[mid];
+            if candidate.from > range.to {
+                return;
+            }
+            if candidate.overlaps(range) {
+                found.push(*id);
+            }
+            self.collect(mid + 1, hi, range, found);
         }
+
+        fn overlapping(&self, range: &Range) -> Vec<ElfId> {
+            let mut found = Vec::new();
+            self.collect(0, self.entries.len(), range, &mut found);
+            found.sort();
+            found
+        }
+
+        fn containing(&self, section: u64) -> Vec<ElfId> {
+            self.overlapping(&Range {
+                from: section,
+                to: section,
+            })
+        }
+    }
+
+    fn solution_part1(input: &str) -> Result<usize, ParseError<ParsePairError>> {
+        let rows: Vec<Pair> = parse_lines(input)?;
+        Ok(rows.iter().filter(|pair| pair.fully_contains()).count())
+    }
+
+    fn solution_part2(input: &str) -> Result<usize, ParseError<ParsePairError>> {
+        let rows: Vec<Pair> = parse_lines(input)?;
+        Ok(rows.iter().filter(|pair| pair.overlaps()).count())
     }
 
     #[test]
     fn test_part1_example() {
-        assert_eq!(solution_part1(EXAMPLE), 2);
+        assert_eq!(solution_part1(EXAMPLE), Ok(2));
     }
 
     #[test]
     fn test_part1_solution() {
-        assert_eq!(solution_part1(INPUT), 496);
+        assert_eq!(solution_part1(INPUT), Ok(496));
     }
 
     #[test]
     fn test_part2_example() {
-        assert_eq!(solution_part2(EXAMPLE), 4);
+        assert_eq!(solution_part2(EXAMPLE), Ok(4));
     }
 
     #[test]
     fn test_part2_solution() {
-        assert_eq!(solution_part2(INPUT), 847);
+        assert_eq!(solution_part2(INPUT), Ok(847));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            solution_part1("2-4,6-8\n2-3"),
+            Err(ParseError {
+                line: 2,
+                error: ParsePairError::InvalidRangeCount(1, "2-3".to_owned()),
+            })
+        );
+        assert_eq!(
+            solution_part1("2-4,6-8\n2-3,4-5,6-7"),
+            Err(ParseError {
+                line: 2,
+                error: ParsePairError::InvalidRangeCount(3, "2-3,4-5,6-7".to_owned()),
+            })
+        );
+        assert_eq!(
+            solution_part2("2-4,68"),
+            Err(ParseError {
+                line: 1,
+                error: ParseRangeError::MissingDash("68".to_owned()).into(),
+            })
+        );
+        assert_eq!(
+            solution_part2("2-4,6-x"),
+            Err(ParseError {
+                line: 1,
+                error: ParseRangeError::InvalidBound("x".to_owned(), "6-x".to_owned()).into(),
+            })
+        );
+        assert_eq!(
+            solution_part2("2-4,6-18446744073709551615")
+                .unwrap_err()
+                .to_string(),
+            "Line 1: Section 18446744073709551615 in range \"6-18446744073709551615\" is above \
+             the largest section 18446744073709551614"
+        );
+        assert_eq!(
+            solution_part2("\n2-4,8-6"),
+            Err(ParseError {
+                line: 1,
+                error: ParsePairError::InvalidRangeCount(1, "".to_owned()),
+            })
+        );
+        assert_eq!(
+            solution_part2("2-4,8-6").unwrap_err().to_string(),
+            "Line 1: The range \"8-6\" starts after it ends"
+        );
+    }
+
+    #[test]
+    fn test_pair_preserves_order() {
+        assert_eq!(
+            Pair::from_str("2-4,6-8"),
+            Ok(Pair(Range { from: 2, to: 4 }, Range { from: 6, to: 8 }))
+        );
+    }
+
+    #[test]
+    fn test_pair_directional_queries() {
+        let pair = Pair::from_str("2-8,3-7").unwrap();
+        assert_eq!(pair.containment(), Containment::FirstContainsSecond);
+        assert_eq!(pair.overlap_size(), 5);
+        assert_eq!(pair.reassignment(), Reassignment::Unassign(1));
+
+        let pair = Pair::from_str("6-6,4-6").unwrap();
+        assert_eq!(pair.containment(), Containment::SecondContainsFirst);
+        assert_eq!(pair.reassignment(), Reassignment::Unassign(0));
+
+        let pair = Pair::from_str("5-7,7-9").unwrap();
+        assert_eq!(pair.containment(), Containment::Neither);
+        assert_eq!(pair.overlap_size(), 1);
+        assert_eq!(
+            pair.reassignment(),
+            Reassignment::Trim(1, Range { from: 8, to: 9 })
+        );
+
+        let pair = Pair::from_str("2-4,6-8").unwrap();
+        assert_eq!(pair.overlap_size(), 0);
+        assert_eq!(pair.reassignment(), Reassignment::Keep);
+    }
+
+    fn range() -> impl Strategy<Value = Range> {
+        (1u64..100, 0u64..50).prop_map(|(from, len)| Range {
+            from,
+            to: from + len,
+        })
+    }
+
+    fn pair() -> impl Strategy<Value = Pair> {
+        (range(), range()).prop_map(|(first, second)| Pair(first, second))
+    }
+
+    fn groups() -> impl Strategy<Value = Vec<Group>> {
+        prop::collection::vec(prop::collection::vec(range(), 1..4).prop_map(Group), 0..20)
+    }
+
+    // Brute-force counterpart of the section index: every assignment is checked in turn.
+    fn overlapping(groups: &[Group], query: &Range) -> Vec<ElfId> {
+        groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, Group(ranges))| {
+                ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.overlaps(query))
+                    .map(move |(elf, _)| ElfId { group, elf })
+            })
+            .collect()
+    }
+
+    // Brute-force counterpart of the arithmetic checks: every section is listed explicitly.
+    fn sections(ranges: &[Range]) -> BTreeSet<u64> {
+        ranges.iter().flat_map(|r| r.from..=r.to).collect()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_pair_symmetric_queries(pair in pair()) {
+            let swapped = pair.swapped();
+            prop_assert_eq!(pair.overlaps(), swapped.overlaps());
+            prop_assert_eq!(pair.fully_contains(), swapped.fully_contains());
+            prop_assert_eq!(pair.overlap_size(), swapped.overlap_size());
+        }
+
+        #[test]
+        fn prop_pair_containment_mirrors(pair in pair()) {
+            let expected = match pair.containment() {
+                Containment::FirstContainsSecond => Containment::SecondContainsFirst,
+                Containment::SecondContainsFirst => Containment::FirstContainsSecond,
+                other => other,
+            };
+            prop_assert_eq!(pair.swapped().containment(), expected);
+        }
+
+        #[test]
+        fn prop_reassignment_removes_redundancy(pair in pair()) {
+            let ranges = [pair.0, pair.1];
+            let reassigned: Vec<Range> = match pair.reassignment() {
+                Reassignment::Keep => ranges.to_vec(),
+                Reassignment::Unassign(elf) => vec![ranges[1 - elf]],
+                Reassignment::Trim(elf, range) => vec![ranges[1 - elf], range],
+            };
+            prop_assert_eq!(sections(&reassigned), sections(&ranges));
+            let total: u64 = reassigned.iter().map(Range::len).sum();
+            prop_assert_eq!(total, sections(&ranges).len() as u64);
+        }
+
+        #[test]
+        fn prop_fully_contains_implies_overlaps(a in range(), offset in 0u64..50, len in 0u64..50) {
+            let from = a.from + offset % a.len();
+            let b = Range { from, to: from + len % (a.to - from + 1) };
+            prop_assert!(a.fully_contains(&b));
+            prop_assert!(a.overlaps(&b));
+        }
+
+        #[test]
+        fn prop_overlaps_is_symmetric(a in range(), b in range()) {
+            prop_assert_eq!(a.overlaps(&b), b.overlaps(&a));
+            prop_assert_eq!(a.intersection(&b), b.intersection(&a));
+        }
+
+        #[test]
+        fn prop_fully_contains_is_antisymmetric(a in range(), b in range()) {
+            prop_assert_eq!(a.fully_contains(&b) && b.fully_contains(&a), a == b);
+        }
+
+        #[test]
+        fn prop_range_matches_sections(a in range(), b in range()) {
+            let (sa, sb) = (sections(&[a]), sections(&[b]));
+            prop_assert_eq!(a.len(), sa.len() as u64);
+            prop_assert_eq!(a.fully_contains(&b), sa.is_superset(&sb));
+            prop_assert_eq!(a.overlaps(&b), !sa.is_disjoint(&sb));
+            let shared: BTreeSet<u64> = sa.intersection(&sb).copied().collect();
+            let intersection = a.intersection(&b).map(|r| sections(&[r])).unwrap_or_default();
+            prop_assert_eq!(intersection, shared);
+        }
+
+        #[test]
+        fn prop_pair_matches_sections(pair in pair()) {
+            let (s0, s1) = (sections(&[pair.0]), sections(&[pair.1]));
+            prop_assert_eq!(pair.fully_contains(), s0.is_superset(&s1) || s1.is_superset(&s0));
+            prop_assert_eq!(pair.overlaps(), !s0.is_disjoint(&s1));
+            prop_assert_eq!(pair.overlap_size(), s0.intersection(&s1).count() as u64);
+        }
+
+        #[test]
+        fn prop_range_parse_round_trip(a in range()) {
+            prop_assert_eq!(Range::from_str(&format!("{}-{}", a.from, a.to)), Ok(a));
+        }
+
+        #[test]
+        fn prop_coverage_matches_sections(groups in groups(), depth in 0usize..4) {
+            let ranges: Vec<Range> = groups.iter().flat_map(|Group(r)| r.iter().copied()).collect();
+            let covered = sections(&ranges);
+            let coverage = Coverage::new(&groups);
+            let deep: BTreeSet<u64> = covered
+                .iter()
+                .copied()
+                .filter(|s| ranges.iter().filter(|r| r.from <= *s && *s <= r.to).count() > depth)
+                .collect();
+            prop_assert_eq!(sections(&coverage.covered_by_more_than(depth)), deep);
+            let uncovered: BTreeSet<u64> = match (covered.first(), covered.last()) {
+                (Some(&lo), Some(&hi)) => (lo..=hi).filter(|s| !covered.contains(s)).collect(),
+                _ => BTreeSet::new(),
+            };
+            prop_assert_eq!(sections(&coverage.uncovered()), uncovered);
+        }
+
+        #[test]
+        fn prop_section_index_matches_brute_force(groups in groups(), query in range()) {
+            let index = SectionIndex::new(&groups);
+            prop_assert_eq!(index.overlapping(&query), overlapping(&groups, &query));
+        }
+    }
+
+    #[test]
+    fn test_coverage_example() {
+        let groups: Vec<Group> = parse_lines(EXAMPLE).unwrap();
+        let coverage = Coverage::new(&groups);
+        assert_eq!(coverage.uncovered(), vec![]);
+        assert_eq!(
+            coverage.covered_by_more_than(5),
+            vec![Range { from: 4, to: 7 }]
+        );
+        assert_eq!(
+            coverage.covered_by_more_than(6),
+            vec![Range { from: 4, to: 6 }]
+        );
+        let deepest = coverage.max_depth();
+        assert_eq!(deepest.len(), 1);
+        assert_eq!(deepest[0].0, Range { from: 6, to: 6 });
+        assert_eq!(deepest[0].1.len(), 8);
+        assert_eq!(Coverage::new(&[]).max_depth(), vec![]);
+    }
+
+    #[test]
+    fn test_coverage_largest_sections() {
+        let range = Range::from_str("0-18446744073709551614").unwrap();
+        assert_eq!(range.len(), u64::MAX);
+        let groups = vec![
+            Group(vec![range]),
+            Group(vec![Range::from_str("5-18446744073709551614").unwrap()]),
+        ];
+        let coverage = Coverage::new(&groups);
+        assert_eq!(coverage.uncovered(), vec![]);
+        assert_eq!(
+            coverage.covered_by_more_than(1),
+            vec![Range {
+                from: 5,
+                to: MAX_SECTION
+            }]
+        );
+    }
+
+    #[test]
+    fn test_coverage_many_elves_per_line() {
+        let groups: Vec<Group> = ["1-3,2-5,4-4", "8-9"]
+            .iter()
+            .map(|l| l.parse().unwrap())
+            .collect();
+        let coverage = Coverage::new(&groups);
+        assert_eq!(coverage.uncovered(), vec![Range { from: 6, to: 7 }]);
+        assert_eq!(
+            coverage.covered_by_more_than(1),
+            vec![Range { from: 2, to: 4 }]
+        );
+        let deepest: Vec<(Range, Vec<ElfId>)> = coverage
+            .max_depth()
+            .into_iter()
+            .map(|(range, elves)| (range, elves.iter().copied().collect()))
+            .collect();
+        assert_eq!(
+            deepest,
+            vec![
+                (
+                    Range { from: 2, to: 3 },
+                    vec![ElfId { group: 0, elf: 0 }, ElfId { group: 0, elf: 1 }]
+                ),
+                (
+                    Range { from: 4, to: 4 },
+                    vec![ElfId { group: 0, elf: 1 }, ElfId { group: 0, elf: 2 }]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_section_index_example() {
+        let groups: Vec<Group> = parse_lines(EXAMPLE).unwrap();
+        let index = SectionIndex::new(&groups);
+        assert_eq!(index.containing(9), vec![ElfId { group: 2, elf: 1 }]);
+        assert_eq!(
+            index.overlapping(&Range { from: 1, to: 2 }),
+            vec![
+                ElfId { group: 0, elf: 0 },
+                ElfId { group: 1, elf: 0 },
+                ElfId { group: 3, elf: 0 },
+                ElfId { group: 5, elf: 0 },
+            ]
+        );
+        assert_eq!(index.containing(10), vec![]);
+    }
+
+    #[test]
+    fn test_section_index_solution() {
+        let groups: Vec<Group> = parse_lines(INPUT).unwrap();
+        let index = SectionIndex::new(&groups);
+        for range in [
+            Range { from: 57, to: 57 },
+            Range { from: 1, to: 3 },
+            Range { from: 40, to: 60 },
+            Range { from: 99, to: 120 },
+        ] {
+            assert_eq!(index.overlapping(&range), overlapping(&groups, &range));
+        }
     }
 
     const EXAMPLE: &str = include_str!("example.txt");
@@ -1,18 +1,70 @@
 #[cfg(test)]
 mod tests {
+    use std::cmp::Ordering;
+    use std::collections::{BTreeMap, HashSet, VecDeque};
+    use std::env;
+    use std::fmt;
+    use std::fmt::Display;
+    use std::fs::File;
+    use std::hash::{Hash, Hasher};
+    use std::io;
+    use std::io::{BufWriter, Write};
+    use std::marker::PhantomData;
+    use std::rc::Rc;
     use std::str::FromStr;
-    use std::usize;
+    use std::sync::mpsc;
+    use std::sync::mpsc::Receiver;
+    use std::thread;
+    use std::time::Duration;
 
-    #[derive(Clone, PartialEq)]
-    struct Crate(char);
+    use proptest::prelude::*;
+
+    // Where a crate sits in the drawing: the stack label and the height counted from 1 at
+    // the bottom. Crates that did not come from a drawing have an unknown origin until they
+    // are first put on a ship, which then records where that happened.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct Origin {
+        stack: usize,
+        height: usize,
+    }
+
+    impl Origin {
+        const UNKNOWN: Origin = Origin {
+            stack: 0,
+            height: 0,
+        };
+    }
+
+    // A crate's mark, where it started, and an id that is unique on its ship. The id is 0
+    // until the crate is first put on a ship, which then hands out the next one.
+    #[derive(Debug, Clone)]
+    struct Crate(char, Origin, usize);
 
     impl Crate {
-        const NONE: Crate = Crate(' ');
+        const NONE: Crate = Crate(' ', Origin::UNKNOWN, 0);
+    }
+
+    // Crates compare by their mark only: two crates with the same letter are
+    // interchangeable as far as any arrangement goes, wherever they started. Only
+    // provenance tells them apart, by id.
+
+    impl PartialEq for Crate {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Crate {}
+
+    impl Hash for Crate {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
     }
 
     impl From<char> for Crate {
         fn from(value: char) -> Self {
-            Crate(value)
+            Crate(value, Origin::UNKNOWN, 0)
         }
     }
 
@@ -27,43 +79,156 @@ mod tests {
         }
     }
 
+    #[derive(Debug, PartialEq)]
+    enum ParseShipError {
+        MissingFooter,
+        InvalidLabel(String, usize),
+        DuplicateLabel(usize, usize),
+        MalformedCrate(usize, usize),
+        UnexpectedCharacter(char, usize, usize),
+        UnlabeledCrate(usize, usize),
+        FloatingCrate(usize, usize),
+    }
+
+    impl Display for ParseShipError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParseShipError::MissingFooter => {
+                    write!(f, "The drawing has no footer with stack numbers")
+                }
+                ParseShipError::InvalidLabel(label, line) => {
+                    write!(
+                        f,
+                        "Invalid stack number {:?} in footer on line {}",
+                        label, line
+                    )
+                }
+                ParseShipError::DuplicateLabel(label, line) => {
+                    write!(
+                        f,
+                        "Stack number {} is repeated in footer on line {}",
+                        label, line
+                    )
+                }
+                ParseShipError::MalformedCrate(line, column) => {
+                    write!(
+                        f,
+                        "Malformed crate on line {}, column {}, expected \"[X]\"",
+                        line, column
+                    )
+                }
+                ParseShipError::UnexpectedCharacter(c, line, column) => {
+                    write!(
+                        f,
+                        "Unexpected character {:?} on line {}, column {}",
+                        c, line, column
+                    )
+                }
+                ParseShipError::UnlabeledCrate(line, column) => {
+                    write!(
+                        f,
+                        "Crate on line {}, column {} is not above any stack number",
+                        line, column
+                    )
+                }
+                ParseShipError::FloatingCrate(line, column) => {
+                    write!(
+                        f,
+                        "Crate on line {}, column {} has nothing underneath",
+                        line, column
+                    )
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
     struct Ship {
         stacks: Vec<Vec<Crate>>,
+        labels: Vec<usize>,
+        // Set once the stacks come from a drawing, whose footer names every stack there is.
+        fixed: bool,
+        // The id of the last crate put on the ship.
+        last_id: usize,
     }
 
     impl Ship {
         fn new() -> Self {
-            Ship { stacks: Vec::new() }
+            Ship {
+                stacks: Vec::new(),
+                labels: Vec::new(),
+                fixed: false,
+                last_id: 0,
+            }
+        }
+
+        fn index(&self, label: usize) -> Option<usize> {
+            self.labels.iter().position(|&l| l == label)
         }
 
-        fn pick_crate(&mut self, from: usize) -> Option<Crate> {
-            self.stacks[from - 1].pop()
+        fn stack_index(&self, label: usize) -> Result<usize, MoveError> {
+            self.index(label).ok_or(MoveError::InvalidStack(label))
         }
 
-        fn put_crate(&mut self, to: usize, cr: Crate) {
-            while self.stacks.len() < to {
-                self.stacks.push(Vec::new());
+        fn check_move(&self, from: usize, to: usize, count: usize) -> Result<(), MoveError> {
+            self.stack_index(to)?;
+            let available = self.stacks[self.stack_index(from)?].len();
+            match available {
+                _ if available >= count => Ok(()),
+                0 => Err(MoveError::EmptyStack(from)),
+                _ => Err(MoveError::InsufficientCrates(from, count, available)),
             }
+        }
+
+        fn pick_crate(&mut self, from: usize) -> Result<Crate, MoveError> {
+            let index = self.stack_index(from)?;
+            self.stacks[index].pop().ok_or(MoveError::EmptyStack(from))
+        }
 
+        // A ship built crate by crate grows new stacks on demand, numbered 1, 2, 3...
+        // A loaded ship only has the stacks its footer labels.
+        fn put_crate(&mut self, to: usize, cr: Crate) -> Result<(), MoveError> {
+            if !self.fixed {
+                while self.labels.len() < to {
+                    self.labels.push(self.labels.len() + 1);
+                    self.stacks.push(Vec::new());
+                }
+            }
+
+            let index = self.stack_index(to)?;
             if cr != Crate::NONE {
-                self.stacks[to - 1].push(cr);
+                let mut cr = cr;
+                if cr.1 == Origin::UNKNOWN {
+                    cr.1 = Origin {
+                        stack: to,
+                        height: self.stacks[index].len() + 1,
+                    };
+                }
+                if cr.2 == 0 {
+                    self.last_id += 1;
+                    cr.2 = self.last_id;
+                }
+                self.stacks[index].push(cr);
             }
+            Ok(())
         }
 
-        fn pick_crates(&mut self, from: usize, count: usize) -> Option<Vec<Crate>> {
-            if self.stacks[from - 1].len() < count {
-                return None;
-            }
+        fn pick_crates(&mut self, from: usize, count: usize) -> Result<Vec<Crate>, MoveError> {
+            self.check_move(from, from, count)?;
+            let index = self.stack_index(from)?;
+            let stack = &mut self.stacks[index];
 
-            let first = self.stacks[from - 1].len() - count;
-            let crates: Vec<Crate> = self.stacks[from - 1].iter().skip(first).cloned().collect();
-            self.stacks[from - 1].truncate(first);
+            let first = stack.len() - count;
+            let crates: Vec<Crate> = stack.iter().skip(first).cloned().collect();
+            stack.truncate(first);
 
-            Some(crates)
+            Ok(crates)
         }
 
-        fn put_crates(&mut self, to: usize, crates: &mut Vec<Crate>) {
-            self.stacks[to - 1].append(crates);
+        fn put_crates(&mut self, to: usize, crates: &mut Vec<Crate>) -> Result<(), MoveError> {
+            let index = self.stack_index(to)?;
+            self.stacks[index].append(crates);
+            Ok(())
         }
 
         fn top_view(&self) -> String {
@@ -74,47 +239,338 @@ mod tests {
             )
         }
 
-        fn load(&mut self, layout: Vec<&str>) {
-            for line in layout.into_iter().rev().skip(1) {
-                for (i, cr) in line.chars().skip(1).step_by(4).enumerate() {
-                    self.put_crate(i + 1, Crate::from(cr));
+        // The footer is the source of truth for the stacks: every label claims the columns
+        // it is written in, and a crate belongs to the stack whose label shares a column
+        // with it. Line and column numbers in errors are 1-based.
+        fn load(&mut self, layout: Vec<&str>) -> Result<(), ParseShipError> {
+            let (footer, rows) = layout.split_last().ok_or(ParseShipError::MissingFooter)?;
+            let footer_line = rows.len() + 1;
+
+            let mut columns: Vec<(usize, usize)> = Vec::new();
+            let mut labels: Vec<usize> = Vec::new();
+            for (start, token) in tokens(footer) {
+                let label = usize::from_str(token)
+                    .map_err(|_| ParseShipError::InvalidLabel(token.to_owned(), footer_line))?;
+                if labels.contains(&label) {
+                    return Err(ParseShipError::DuplicateLabel(label, footer_line));
+                }
+                columns.push((start, start + token.len() - 1));
+                labels.push(label);
+            }
+            if labels.is_empty() {
+                return Err(ParseShipError::MissingFooter);
+            }
+
+            let mut stacks: Vec<Vec<Crate>> = vec![Vec::new(); labels.len()];
+            let mut last_id = 0;
+            for (line, row) in rows.iter().enumerate().rev() {
+                for (start, token) in tokens(row) {
+                    let chars: Vec<char> = token.chars().collect();
+                    if chars[0] != '[' {
+                        return Err(ParseShipError::UnexpectedCharacter(
+                            chars[0],
+                            line + 1,
+                            start + 1,
+                        ));
+                    }
+                    if chars.len() != 3 || chars[2] != ']' || !chars[1].is_ascii_alphabetic() {
+                        return Err(ParseShipError::MalformedCrate(line + 1, start + 1));
+                    }
+                    let stack = columns
+                        .iter()
+                        .position(|&(from, to)| from <= start + 2 && start <= to)
+                        .ok_or(ParseShipError::UnlabeledCrate(line + 1, start + 1))?;
+                    if stacks[stack].len() != rows.len() - line - 1 {
+                        return Err(ParseShipError::FloatingCrate(line + 1, start + 1));
+                    }
+                    let origin = Origin {
+                        stack: labels[stack],
+                        height: stacks[stack].len() + 1,
+                    };
+                    last_id += 1;
+                    stacks[stack].push(Crate(chars[1], origin, last_id));
+                }
+            }
+
+            self.labels = labels;
+            self.stacks = stacks;
+            self.fixed = true;
+            self.last_id = last_id;
+            Ok(())
+        }
+    }
+
+    // Renders the ship the way the puzzle draws it: every stack takes a column wide enough
+    // for its crates and its label, and the footer has no trailing whitespace.
+    impl Display for Ship {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            Frame {
+                ship: self,
+                moved: None,
+                ansi: false,
+            }
+            .fmt(f)
+        }
+    }
+
+    // A ship drawing with the crates moved by the last instruction highlighted: in bold
+    // yellow on a terminal, or with curly braces instead of brackets in plain text.
+    struct Frame<'a> {
+        ship: &'a Ship,
+        moved: Option<(usize, usize)>,
+        ansi: bool,
+    }
+
+    impl Frame<'_> {
+        fn is_moved(&self, stack: usize, level: usize) -> bool {
+            self.moved.is_some_and(|(label, count)| {
+                self.ship.index(label) == Some(stack)
+                    && level + count >= self.ship.stacks[stack].len()
+            })
+        }
+    }
+
+    impl Display for Frame<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let widths: Vec<usize> = self
+                .ship
+                .labels
+                .iter()
+                .map(|label| label.to_string().len().max(3))
+                .collect();
+            let height = self.ship.stacks.iter().map(Vec::len).max().unwrap_or(0);
+            for level in (0..height).rev() {
+                let row: Vec<String> = self
+                    .ship
+                    .stacks
+                    .iter()
+                    .zip(&widths)
+                    .enumerate()
+                    .map(|(i, (stack, &width))| match stack.get(level) {
+                        Some(cr) if self.is_moved(i, level) && self.ansi => {
+                            let padded = format!("{:^width$}", format!("[{}]", cr.0));
+                            padded.replace('[', "\x1b[1;33m[").replace(']', "]\x1b[0m")
+                        }
+                        Some(cr) if self.is_moved(i, level) => {
+                            format!("{:^width$}", format!("{{{}}}", cr.0))
+                        }
+                        Some(cr) => format!("{:^width$}", format!("[{}]", cr.0)),
+                        None => " ".repeat(width),
+                    })
+                    .collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
+            let footer: Vec<String> = self
+                .ship
+                .labels
+                .iter()
+                .zip(&widths)
+                .map(|(label, &width)| format!("{:^width$}", label))
+                .collect();
+            write!(f, "{}", footer.join(" ").trim_end())
+        }
+    }
+
+    // Splits a line into runs of non-space characters along with their starting column.
+    fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+        line.char_indices()
+            .filter(move |&(i, c)| c != ' ' && (i == 0 || line.as_bytes()[i - 1] == b' '))
+            .map(move |(i, _)| {
+                let end = line[i..].find(' ').map_or(line.len(), |len| i + len);
+                (i, &line[i..end])
+            })
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum MoveError {
+        InvalidStack(usize),
+        EmptyStack(usize),
+        InsufficientCrates(usize, usize, usize),
+        StackTooHigh(usize, usize),
+    }
+
+    impl Display for MoveError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                MoveError::InvalidStack(stack) => write!(f, "There is no stack {}", stack),
+                MoveError::EmptyStack(stack) => write!(f, "Stack {} is empty", stack),
+                MoveError::InsufficientCrates(stack, requested, available) => write!(
+                    f,
+                    "Cannot move {} crates from stack {}, it only has {}",
+                    requested, stack, available
+                ),
+                MoveError::StackTooHigh(stack, height) => {
+                    write!(f, "Stack {} cannot hold more than {} crates", stack, height)
                 }
             }
         }
     }
 
+    #[derive(Debug, PartialEq)]
+    struct SimulationError {
+        instruction: usize,
+        error: MoveError,
+    }
+
+    impl Display for SimulationError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Instruction {}: {}", self.instruction, self.error)
+        }
+    }
+
+    // Movers validate the whole instruction up front, so a failed move leaves the ship
+    // untouched.
     trait CrateMover {
-        fn move_crates(ship: &mut Ship, from: usize, to: usize, count: usize);
+        fn move_crates(
+            ship: &mut Ship,
+            from: usize,
+            to: usize,
+            count: usize,
+        ) -> Result<(), MoveError>;
+
+        // Replaces two instructions executed back to back with an equivalent (possibly
+        // empty) sequence, or returns `None` when this mover cannot simplify them.
+        fn combine(_first: &Instruction, _second: &Instruction) -> Option<Vec<Instruction>> {
+            None
+        }
     }
 
     struct CrateMover9000;
 
     impl CrateMover for CrateMover9000 {
-        fn move_crates(ship: &mut Ship, from: usize, to: usize, count: usize) {
+        fn move_crates(
+            ship: &mut Ship,
+            from: usize,
+            to: usize,
+            count: usize,
+        ) -> Result<(), MoveError> {
+            ship.check_move(from, to, count)?;
             for _ in 0..count {
-                let cr = ship.pick_crate(from).unwrap();
-                ship.put_crate(to, cr);
+                let cr = ship.pick_crate(from)?;
+                ship.put_crate(to, cr)?;
+            }
+            Ok(())
+        }
+
+        // Every instruction is a run of single-crate moves, so runs in the same direction
+        // add up and runs in opposite directions cancel out crate by crate.
+        fn combine(first: &Instruction, second: &Instruction) -> Option<Vec<Instruction>> {
+            if first.from == second.from && first.to == second.to {
+                return Some(vec![Instruction {
+                    count: first.count + second.count,
+                    ..*first
+                }]);
+            }
+            if first.from != second.to || first.to != second.from {
+                return None;
             }
+            Some(match first.count.cmp(&second.count) {
+                Ordering::Greater => vec![Instruction {
+                    count: first.count - second.count,
+                    ..*first
+                }],
+                Ordering::Less => vec![Instruction {
+                    count: second.count - first.count,
+                    ..*second
+                }],
+                Ordering::Equal => vec![],
+            })
         }
     }
 
     struct CrateMover9001;
 
     impl CrateMover for CrateMover9001 {
-        fn move_crates(ship: &mut Ship, from: usize, to: usize, count: usize) {
-            let mut crates = ship.pick_crates(from, count).unwrap();
-            ship.put_crates(to, &mut crates);
+        fn move_crates(
+            ship: &mut Ship,
+            from: usize,
+            to: usize,
+            count: usize,
+        ) -> Result<(), MoveError> {
+            ship.check_move(from, to, count)?;
+            let mut crates = ship.pick_crates(from, count)?;
+            ship.put_crates(to, &mut crates)
+        }
+
+        // Crates keep their order, so only moving the same block straight back is a no-op.
+        fn combine(first: &Instruction, second: &Instruction) -> Option<Vec<Instruction>> {
+            if first.from == second.to && first.to == second.from && first.count == second.count {
+                return Some(vec![]);
+            }
+            None
         }
     }
 
-    #[derive(Debug)]
+    // Lifts at most CAPACITY crates at a time, keeping their order like the CrateMover 9001,
+    // so larger moves turn into several lifts, with the top crates going first.
+    struct CappedCrateMover<const CAPACITY: usize>;
+
+    impl<const CAPACITY: usize> CrateMover for CappedCrateMover<CAPACITY> {
+        fn move_crates(
+            ship: &mut Ship,
+            from: usize,
+            to: usize,
+            count: usize,
+        ) -> Result<(), MoveError> {
+            const { assert!(CAPACITY > 0, "The crane must lift at least one crate") };
+            ship.check_move(from, to, count)?;
+            let mut remaining = count;
+            while remaining > 0 {
+                let lift = remaining.min(CAPACITY);
+                let mut crates = ship.pick_crates(from, lift)?;
+                ship.put_crates(to, &mut crates)?;
+                remaining -= lift;
+            }
+            Ok(())
+        }
+    }
+
+    // Keeps the order of up to THRESHOLD crates like the CrateMover 9001, but moves anything
+    // larger one crate at a time like the CrateMover 9000, flipping the order.
+    struct ThresholdCrateMover<const THRESHOLD: usize>;
+
+    impl<const THRESHOLD: usize> CrateMover for ThresholdCrateMover<THRESHOLD> {
+        fn move_crates(
+            ship: &mut Ship,
+            from: usize,
+            to: usize,
+            count: usize,
+        ) -> Result<(), MoveError> {
+            if count > THRESHOLD {
+                CrateMover9000::move_crates(ship, from, to, count)
+            } else {
+                CrateMover9001::move_crates(ship, from, to, count)
+            }
+        }
+    }
+
+    // Moves crates like the wrapped mover T, but refuses any move that would leave the
+    // target stack with more than HEIGHT crates. Stacks that start out taller are allowed.
+    struct HeightLimitedCrateMover<T, const HEIGHT: usize>(PhantomData<T>);
+
+    impl<T: CrateMover, const HEIGHT: usize> CrateMover for HeightLimitedCrateMover<T, HEIGHT> {
+        fn move_crates(
+            ship: &mut Ship,
+            from: usize,
+            to: usize,
+            count: usize,
+        ) -> Result<(), MoveError> {
+            ship.check_move(from, to, count)?;
+            if from != to && ship.stacks[ship.stack_index(to)?].len() + count > HEIGHT {
+                return Err(MoveError::StackTooHigh(to, HEIGHT));
+            }
+            T::move_crates(ship, from, to, count)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
     struct Instruction {
         from: usize,
         to: usize,
         count: usize,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, PartialEq)]
     enum ParseInstructionError {
         IncorrectFormat(String),
         InvalidCount(String),
@@ -122,6 +578,23 @@ mod tests {
         InvalidTo(String),
     }
 
+    impl Display for ParseInstructionError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParseInstructionError::IncorrectFormat(message)
+                | ParseInstructionError::InvalidCount(message)
+                | ParseInstructionError::InvalidFrom(message)
+                | ParseInstructionError::InvalidTo(message) => write!(f, "{}", message),
+            }
+        }
+    }
+
+    impl Display for Instruction {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "move {} from {} to {}", self.count, self.from, self.to)
+        }
+    }
+
     impl FromStr for Instruction {
         type Err = ParseInstructionError;
 
@@ -156,65 +629,1713 @@ mod tests {
         }
     }
 
-    fn solution<T>(input: &str) -> String
+    #[derive(Debug, PartialEq)]
+    enum InputError {
+        InvalidDrawing(ParseShipError),
+        // The line number counts from the top of the input, drawing included.
+        InvalidInstruction(usize, ParseInstructionError),
+    }
+
+    impl Display for InputError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                InputError::InvalidDrawing(err) => write!(f, "{}", err),
+                InputError::InvalidInstruction(line, err) => write!(f, "Line {}: {}", line, err),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum SolveError {
+        InvalidInput(InputError),
+        FailedMove(SimulationError),
+    }
+
+    impl Display for SolveError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SolveError::InvalidInput(err) => write!(f, "{}", err),
+                SolveError::FailedMove(err) => write!(f, "{}", err),
+            }
+        }
+    }
+
+    impl From<InputError> for SolveError {
+        fn from(err: InputError) -> Self {
+            SolveError::InvalidInput(err)
+        }
+    }
+
+    impl From<SimulationError> for SolveError {
+        fn from(err: SimulationError) -> Self {
+            SolveError::FailedMove(err)
+        }
+    }
+
+    fn parse_input(input: &str) -> Result<(Ship, Vec<Instruction>), InputError> {
+        let mut liter = input.lines().enumerate();
+        let mut ship = Ship::new();
+
+        ship.load(
+            liter
+                .by_ref()
+                .map(|(_, line)| line)
+                .take_while(|line| !line.is_empty())
+                .collect(),
+        )
+        .map_err(InputError::InvalidDrawing)?;
+
+        let instructions = liter
+            .map(|(i, line)| {
+                Instruction::from_str(line)
+                    .map_err(|err| InputError::InvalidInstruction(i + 1, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((ship, instructions))
+    }
+
+    // Instructions are numbered from 1 in errors, matching the animation titles.
+    fn execute<T>(
+        ship: &mut Ship,
+        instructions: &[Instruction],
+        mut observe: impl FnMut(&Ship),
+    ) -> Result<(), SimulationError>
     where
         T: CrateMover,
     {
-        let mut liter = input.lines();
-        let mut ship = Ship::new();
+        for (i, instruction) in instructions.iter().enumerate() {
+            T::move_crates(ship, instruction.from, instruction.to, instruction.count).map_err(
+                |error| SimulationError {
+                    instruction: i + 1,
+                    error,
+                },
+            )?;
+            observe(ship);
+        }
+        Ok(())
+    }
+
+    fn simulate<T>(input: &str, observe: impl FnMut(&Ship)) -> Result<Ship, SolveError>
+    where
+        T: CrateMover,
+    {
+        let (mut ship, instructions) = parse_input(input)?;
+        execute::<T>(&mut ship, &instructions, observe)?;
+        Ok(ship)
+    }
+
+    // Produces a shorter program with the same final state, assuming the original runs
+    // without errors. Moves that do nothing are dropped, and every instruction is combined
+    // with the closest earlier one touching the same stacks; instructions on other stacks
+    // in between are independent, so they do not get in the way.
+    fn optimize<T>(instructions: &[Instruction]) -> Vec<Instruction>
+    where
+        T: CrateMover,
+    {
+        fn push<T: CrateMover>(program: &mut Vec<Instruction>, instruction: Instruction) {
+            if instruction.count == 0 || instruction.from == instruction.to {
+                return;
+            }
+            let touches = |other: &Instruction| {
+                [other.from, other.to].contains(&instruction.from)
+                    || [other.from, other.to].contains(&instruction.to)
+            };
+            if let Some(i) = program.iter().rposition(touches) {
+                if let Some(replacement) = T::combine(&program[i], &instruction) {
+                    program.remove(i);
+                    for instruction in replacement {
+                        push::<T>(program, instruction);
+                    }
+                    return;
+                }
+            }
+            program.push(instruction);
+        }
+
+        let mut program = Vec::new();
+        for instruction in instructions {
+            push::<T>(&mut program, *instruction);
+        }
+        program
+    }
+
+    // An applied instruction along with the crates it took off the source stack and the
+    // crates it left on the target stack, both listed bottom to top.
+    struct Record {
+        instruction: Instruction,
+        picked: Vec<Crate>,
+        placed: Vec<Crate>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct StackChange {
+        stack: usize,
+        removed: Vec<Crate>,
+        added: Vec<Crate>,
+    }
+
+    // Runs a program one instruction at a time in either direction. Positions count the
+    // applied instructions, so position 0 is the initial drawing. Stepping back relies on
+    // the records only, which makes undo work the same way for every crane model.
+    struct Timeline<T> {
+        ship: Ship,
+        program: Vec<Instruction>,
+        records: Vec<Record>,
+        position: usize,
+        mover: PhantomData<T>,
+    }
+
+    impl<T: CrateMover> Timeline<T> {
+        fn new(ship: Ship, program: Vec<Instruction>) -> Self {
+            Timeline {
+                ship,
+                program,
+                records: Vec::new(),
+                position: 0,
+                mover: PhantomData,
+            }
+        }
+
+        fn stack(&mut self, label: usize) -> &mut Vec<Crate> {
+            let index = self.ship.index(label).unwrap();
+            &mut self.ship.stacks[index]
+        }
+
+        fn step_forward(&mut self) -> Result<bool, SimulationError> {
+            if let Some(record) = self.records.get(self.position) {
+                let Instruction { from, to, count } = record.instruction;
+                let placed = record.placed.clone();
+                let from = self.stack(from);
+                from.truncate(from.len() - count);
+                self.stack(to).extend(placed);
+            } else if let Some(&instruction) = self.program.get(self.position) {
+                let Instruction { from, to, count } = instruction;
+                let position = self.position;
+                let error = |error| SimulationError {
+                    instruction: position + 1,
+                    error,
+                };
+                self.ship.check_move(from, to, count).map_err(error)?;
+                let source = self.stack(from);
+                let picked = source[source.len() - count..].to_vec();
+                T::move_crates(&mut self.ship, from, to, count).map_err(error)?;
+                let target = self.stack(to);
+                let placed = target[target.len() - count..].to_vec();
+                self.records.push(Record {
+                    instruction,
+                    picked,
+                    placed,
+                });
+            } else {
+                return Ok(false);
+            }
+            self.position += 1;
+            Ok(true)
+        }
+
+        fn step_backward(&mut self) -> bool {
+            if self.position == 0 {
+                return false;
+            }
+            self.position -= 1;
+            let record = &self.records[self.position];
+            let Instruction { from, to, count } = record.instruction;
+            let picked = record.picked.clone();
+            let to = self.stack(to);
+            to.truncate(to.len() - count);
+            self.stack(from).extend(picked);
+            true
+        }
+
+        fn seek(&mut self, position: usize) -> Result<&Ship, SimulationError> {
+            while self.position > position {
+                self.step_backward();
+            }
+            while self.position < position && self.step_forward()? {}
+            Ok(&self.ship)
+        }
+
+        // Lists the stacks that differ between two positions, leaving out the crates at the
+        // bottom that both states share. A crate replaced by another one with the same
+        // letter does not count as a change.
+        fn diff(&mut self, from: usize, to: usize) -> Result<Vec<StackChange>, SimulationError> {
+            let before = self.seek(from)?.clone();
+            let after = self.seek(to)?;
+            Ok(before
+                .labels
+                .iter()
+                .zip(before.stacks.iter().zip(&after.stacks))
+                .filter(|(_, (before, after))| before != after)
+                .map(|(&stack, (before, after))| {
+                    let common = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+                    StackChange {
+                        stack,
+                        removed: before[common..].to_vec(),
+                        added: after[common..].to_vec(),
+                    }
+                })
+                .collect())
+        }
+
+        // Runs the whole program and traces every crate through it, ordered by origin.
+        fn provenance(&mut self) -> Result<Vec<Journey>, SimulationError> {
+            self.seek(usize::MAX)?;
+            let mut journeys: BTreeMap<usize, Journey> = self
+                .ship
+                .stacks
+                .iter()
+                .flatten()
+                .map(|cr| {
+                    let journey = Journey {
+                        id: cr.2,
+                        mark: cr.0,
+                        origin: cr.1,
+                        moves: Vec::new(),
+                    };
+                    (cr.2, journey)
+                })
+                .collect();
+            for (i, record) in self.records.iter().enumerate() {
+                for cr in &record.placed {
+                    if let Some(journey) = journeys.get_mut(&cr.2) {
+                        journey.moves.push((i + 1, record.instruction.to));
+                    }
+                }
+            }
+            let mut journeys: Vec<Journey> = journeys.into_values().collect();
+            journeys.sort_by_key(|journey| journey.origin);
+            Ok(journeys)
+        }
+    }
+
+    // Every stack a crate was put on, along with the instruction that moved it there.
+    struct Journey {
+        id: usize,
+        mark: char,
+        origin: Origin,
+        moves: Vec<(usize, usize)>,
+    }
+
+    impl Display for Journey {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "[{}] from stack {}, height {}:",
+                self.mark, self.origin.stack, self.origin.height
+            )?;
+            if self.moves.is_empty() {
+                return write!(f, " never moved");
+            }
+            let moves: Vec<String> = self
+                .moves
+                .iter()
+                .map(|(instruction, stack)| format!("stack {} after move {}", stack, instruction))
+                .collect();
+            write!(f, " {}", moves.join(", "))
+        }
+    }
+
+    // What a plan has to reach: an exact arrangement, or just the crates on top.
+    enum Goal {
+        Arrangement(Vec<Vec<Crate>>),
+        TopView(String),
+    }
+
+    impl Goal {
+        fn is_reached(&self, ship: &Ship) -> bool {
+            match self {
+                Goal::Arrangement(stacks) => ship.stacks == *stacks,
+                Goal::TopView(view) => ship.top_view() == *view,
+            }
+        }
+    }
+
+    // Breadth-first search over ship states, so the first plan found is one of the
+    // shortest. Every move of any number of crates between two different stacks counts as
+    // a single instruction.
+    fn plan<T>(ship: &Ship, goal: &Goal, max_depth: usize) -> Option<Vec<Instruction>>
+    where
+        T: CrateMover,
+    {
+        let mut seen = HashSet::from([ship.stacks.clone()]);
+        let mut queue = VecDeque::from([(ship.clone(), Vec::new())]);
+        while let Some((ship, path)) = queue.pop_front() {
+            if goal.is_reached(&ship) {
+                return Some(path);
+            }
+            if path.len() == max_depth {
+                continue;
+            }
+            for (from_index, &from) in ship.labels.iter().enumerate() {
+                for &to in ship.labels.iter().filter(|&&to| to != from) {
+                    for count in 1..=ship.stacks[from_index].len() {
+                        let mut next = ship.clone();
+                        if T::move_crates(&mut next, from, to, count).is_err() {
+                            continue;
+                        }
+                        if seen.insert(next.stacks.clone()) {
+                            let mut path = path.clone();
+                            path.push(Instruction { from, to, count });
+                            queue.push_back((next, path));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn solution<T>(input: &str) -> Result<String, SolveError>
+    where
+        T: CrateMover,
+    {
+        Ok(simulate::<T>(input, |_| {})?.top_view())
+    }
+
+    // A run of crates borrowed from a shared buffer, read bottom to top either along the
+    // buffer or backwards. Moving crates only moves and splits runs, never the crates.
+    #[derive(Clone)]
+    struct Segment {
+        crates: Rc<[Crate]>,
+        start: usize,
+        end: usize,
+        reversed: bool,
+    }
+
+    impl Segment {
+        fn len(&self) -> usize {
+            self.end - self.start
+        }
+
+        fn bottom(&self) -> &Crate {
+            if self.reversed {
+                &self.crates[self.end - 1]
+            } else {
+                &self.crates[self.start]
+            }
+        }
+
+        fn top(&self) -> &Crate {
+            if self.reversed {
+                &self.crates[self.start]
+            } else {
+                &self.crates[self.end - 1]
+            }
+        }
+
+        fn iter(&self) -> Box<dyn DoubleEndedIterator<Item = &Crate> + '_> {
+            let crates = self.crates[self.start..self.end].iter();
+            if self.reversed {
+                Box::new(crates.rev())
+            } else {
+                Box::new(crates)
+            }
+        }
+
+        // Cuts the run into the crates that stay below and the `count` crates on top.
+        fn split_top(self, count: usize) -> (Segment, Segment) {
+            let bottom = self.len() - count;
+            if self.reversed {
+                let mid = self.end - bottom;
+                let top = Segment {
+                    end: mid,
+                    ..self.clone()
+                };
+                (Segment { start: mid, ..self }, top)
+            } else {
+                let mid = self.start + bottom;
+                let top = Segment {
+                    start: mid,
+                    ..self.clone()
+                };
+                (Segment { end: mid, ..self }, top)
+            }
+        }
+
+        fn flipped(self) -> Segment {
+            Segment {
+                reversed: !self.reversed,
+                ..self
+            }
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    enum Order {
+        Reversed,
+        Preserved,
+    }
+
+    // A node of a stack's treap: its runs read bottom to top in order, left subtree first.
+    // A flipped node still owes its whole subtree a reversal, which is pushed down to the
+    // children only when the node is next taken apart.
+    struct RopeNode {
+        segment: Segment,
+        priority: u64,
+        left: Option<usize>,
+        right: Option<usize>,
+        len: usize,
+        flipped: bool,
+    }
+
+    // Stacks kept as treaps of runs: cutting crates off the top and putting them on another
+    // stack, reversed or not, takes logarithmic time in the number of runs, and every move
+    // adds at most one run, so huge moves stay cheap however the crates were shuffled.
+    struct RopeShip {
+        labels: Vec<usize>,
+        stacks: Vec<Option<usize>>,
+        nodes: Vec<RopeNode>,
+        seed: u64,
+    }
+
+    impl RopeShip {
+        fn stack_index(&self, label: usize) -> Result<usize, MoveError> {
+            self.labels
+                .iter()
+                .position(|&l| l == label)
+                .ok_or(MoveError::InvalidStack(label))
+        }
+
+        fn height(&self, index: usize) -> usize {
+            self.len(self.stacks[index])
+        }
+
+        fn len(&self, node: Option<usize>) -> usize {
+            node.map_or(0, |id| self.nodes[id].len)
+        }
+
+        fn leaf(&mut self, segment: Segment) -> usize {
+            // xorshift64, which is plenty to keep the treap balanced.
+            self.seed ^= self.seed << 13;
+            self.seed ^= self.seed >> 7;
+            self.seed ^= self.seed << 17;
+            self.nodes.push(RopeNode {
+                len: segment.len(),
+                segment,
+                priority: self.seed,
+                left: None,
+                right: None,
+                flipped: false,
+            });
+            self.nodes.len() - 1
+        }
+
+        fn flip(&mut self, node: Option<usize>) {
+            if let Some(id) = node {
+                self.nodes[id].flipped = !self.nodes[id].flipped;
+            }
+        }
+
+        fn push_down(&mut self, id: usize) {
+            if self.nodes[id].flipped {
+                let node = &mut self.nodes[id];
+                node.flipped = false;
+                (node.left, node.right) = (node.right, node.left);
+                node.segment = node.segment.clone().flipped();
+                let (left, right) = (node.left, node.right);
+                self.flip(left);
+                self.flip(right);
+            }
+        }
+
+        fn update(&mut self, id: usize) {
+            let node = &self.nodes[id];
+            let len = self.len(node.left) + node.segment.len() + self.len(node.right);
+            self.nodes[id].len = len;
+        }
+
+        // Splits a stack into its bottom `count` crates and the rest above them.
+        fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+            let Some(id) = node else {
+                return (None, None);
+            };
+            self.push_down(id);
+            let (left, right) = (self.nodes[id].left, self.nodes[id].right);
+            let below = self.len(left);
+            let through = below + self.nodes[id].segment.len();
+            if count <= below {
+                let (bottom, top) = self.split(left, count);
+                self.nodes[id].left = top;
+                self.update(id);
+                (bottom, Some(id))
+            } else if count >= through {
+                let (bottom, top) = self.split(right, count - through);
+                self.nodes[id].right = bottom;
+                self.update(id);
+                (Some(id), top)
+            } else {
+                let segment = self.nodes[id].segment.clone();
+                let (kept, cut) = segment.split_top(through - count);
+                self.nodes[id].segment = kept;
+                self.nodes[id].right = None;
+                self.update(id);
+                let cut = self.leaf(cut);
+                (Some(id), self.merge(Some(cut), right))
+            }
+        }
+
+        // Puts the stack `top` on top of the stack `bottom`.
+        fn merge(&mut self, bottom: Option<usize>, top: Option<usize>) -> Option<usize> {
+            let (Some(b), Some(t)) = (bottom, top) else {
+                return bottom.or(top);
+            };
+            if self.nodes[b].priority > self.nodes[t].priority {
+                self.push_down(b);
+                self.nodes[b].right = self.merge(self.nodes[b].right, top);
+                self.update(b);
+                bottom
+            } else {
+                self.push_down(t);
+                self.nodes[t].left = self.merge(bottom, self.nodes[t].left);
+                self.update(t);
+                top
+            }
+        }
+
+        fn move_crates(
+            &mut self,
+            from: usize,
+            to: usize,
+            count: usize,
+            order: Order,
+        ) -> Result<(), MoveError> {
+            let (source, target) = (self.stack_index(from)?, self.stack_index(to)?);
+            match self.height(source) {
+                available if available >= count => {}
+                0 => return Err(MoveError::EmptyStack(from)),
+                available => return Err(MoveError::InsufficientCrates(from, count, available)),
+            }
+            if source == target {
+                return Ok(());
+            }
+
+            let (kept, taken) = self.split(self.stacks[source], self.height(source) - count);
+            if let Order::Reversed = order {
+                self.flip(taken);
+            }
+            self.stacks[source] = kept;
+            self.stacks[target] = self.merge(self.stacks[target], taken);
+            Ok(())
+        }
+
+        // The top crate of a subtree, read without pushing down pending reversals.
+        fn top(&self, id: usize) -> &Crate {
+            let (mut id, mut flipped) = (id, false);
+            loop {
+                let node = &self.nodes[id];
+                flipped ^= node.flipped;
+                match (flipped, node.left, node.right) {
+                    (true, Some(next), _) | (false, _, Some(next)) => id = next,
+                    (true, None, _) => return node.segment.bottom(),
+                    (false, _, None) => return node.segment.top(),
+                }
+            }
+        }
+
+        fn top_view(&self) -> String {
+            String::from_iter(
+                self.stacks
+                    .iter()
+                    .map(|stack| stack.map_or(&Crate::NONE, |id| self.top(id))),
+            )
+        }
+
+        fn collect(&self, node: Option<usize>, flipped: bool, crates: &mut Vec<Crate>) {
+            let Some(id) = node else {
+                return;
+            };
+            let node = &self.nodes[id];
+            let flipped = flipped ^ node.flipped;
+            if flipped {
+                self.collect(node.right, flipped, crates);
+                crates.extend(node.segment.iter().rev().cloned());
+                self.collect(node.left, flipped, crates);
+            } else {
+                self.collect(node.left, flipped, crates);
+                crates.extend(node.segment.iter().cloned());
+                self.collect(node.right, flipped, crates);
+            }
+        }
+
+        fn stacks(&self) -> Vec<Vec<Crate>> {
+            self.stacks
+                .iter()
+                .map(|&stack| {
+                    let mut crates = Vec::new();
+                    self.collect(stack, false, &mut crates);
+                    crates
+                })
+                .collect()
+        }
+    }
+
+    impl From<&Ship> for RopeShip {
+        fn from(ship: &Ship) -> Self {
+            let mut rope = RopeShip {
+                labels: ship.labels.clone(),
+                stacks: vec![None; ship.stacks.len()],
+                nodes: Vec::new(),
+                seed: 0x2545_f491_4f6c_dd1d,
+            };
+            for (index, stack) in ship.stacks.iter().enumerate() {
+                if !stack.is_empty() {
+                    let segment = Segment {
+                        crates: Rc::from(stack.as_slice()),
+                        start: 0,
+                        end: stack.len(),
+                        reversed: false,
+                    };
+                    rope.stacks[index] = Some(rope.leaf(segment));
+                }
+            }
+            rope
+        }
+    }
+
+    fn rope_solution(input: &str, order: Order) -> Result<String, SolveError> {
+        let (ship, instructions) = parse_input(input)?;
+        let mut rope = RopeShip::from(&ship);
+        for (i, instruction) in instructions.iter().enumerate() {
+            rope.move_crates(instruction.from, instruction.to, instruction.count, order)
+                .map_err(|error| SimulationError {
+                    instruction: i + 1,
+                    error,
+                })?;
+        }
+        Ok(rope.top_view())
+    }
+
+    enum Playback {
+        // Redraws the terminal after every move, waiting between frames. Enter pauses into
+        // stepping and "q" stops the animation.
+        Play(Duration),
+        // Waits for a command after every move: Enter steps, "c" continues playing with
+        // the given delay, and "q" stops the animation.
+        Step(Duration),
+        // Appends plain-text frames one after another, without delays or escape codes.
+        Record,
+    }
+
+    fn animate<T>(
+        input: &str,
+        mut playback: Playback,
+        out: &mut impl Write,
+        controls: &Receiver<String>,
+    ) -> io::Result<()>
+    where
+        T: CrateMover,
+    {
+        let (mut ship, instructions) = parse_input(input)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let ansi = !matches!(playback, Playback::Record);
 
-        ship.load(liter.by_ref().take_while(|line| !line.is_empty()).collect());
+        let draw = |out: &mut dyn Write, title: String, frame: Frame| {
+            if ansi {
+                write!(out, "\x1b[2J\x1b[H")?;
+            }
+            write!(out, "{}\n{}\n\n", title, frame)
+        };
 
-        for instruction in liter {
-            let instruction = Instruction::from_str(instruction).unwrap();
+        let frame = Frame {
+            ship: &ship,
+            moved: None,
+            ansi,
+        };
+        draw(out, "Initial drawing".to_owned(), frame)?;
 
+        for (i, instruction) in instructions.iter().enumerate() {
             T::move_crates(
                 &mut ship,
                 instruction.from,
                 instruction.to,
                 instruction.count,
-            );
+            )
+            .map_err(|error| {
+                let error = SimulationError {
+                    instruction: i + 1,
+                    error,
+                };
+                io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+            })?;
+            let title = format!("Move {}/{}: {}", i + 1, instructions.len(), instruction);
+            let frame = Frame {
+                ship: &ship,
+                moved: Some((instruction.to, instruction.count)),
+                ansi,
+            };
+            draw(out, title, frame)?;
+            if !wait(&mut playback, out, controls)? {
+                break;
+            }
         }
-        ship.top_view()
+        out.flush()
+    }
+
+    // Waits after a frame as the playback asks, and tells whether to go on. Commands are
+    // lines read from the controls; while playing they are only checked between frames,
+    // so the animation never blocks on them.
+    fn wait(
+        playback: &mut Playback,
+        out: &mut impl Write,
+        controls: &Receiver<String>,
+    ) -> io::Result<bool> {
+        if let Playback::Play(delay) = *playback {
+            write!(out, "[Enter] pause, [q] quit")?;
+            out.flush()?;
+            thread::sleep(delay);
+            match controls.try_recv() {
+                Ok(command) if command.trim() == "q" => return Ok(false),
+                Ok(_) => *playback = Playback::Step(delay),
+                Err(_) => return Ok(true),
+            }
+        }
+        if let Playback::Step(delay) = *playback {
+            write!(out, "\r[Enter] next move, [c] continue, [q] quit: ")?;
+            out.flush()?;
+            match controls.recv().unwrap_or_default().trim() {
+                "q" => return Ok(false),
+                "c" => *playback = Playback::Play(delay),
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+
+    // Feeds the given lines to an animation as if they were typed.
+    fn typed(lines: &[&str]) -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        for line in lines {
+            sender.send(line.to_string()).unwrap();
+        }
+        receiver
+    }
+
+    // Diagrams from the puzzle description, in order: the initial drawing (with the
+    // instructions), the four CrateMover 9000 steps, the initial drawing again, and the
+    // four CrateMover 9001 steps.
+    fn puzzle_diagrams() -> Vec<&'static str> {
+        README
+            .split("```\n")
+            .skip(1)
+            .step_by(2)
+            .map(|block| block.trim_end_matches('\n'))
+            .collect()
     }
 
     #[test]
     fn test_ship_empty_stack() {
         let mut ship = Ship::new();
-        ship.put_crate(2, Crate('A'));
+        ship.put_crate(2, Crate::from('A')).unwrap();
         assert_eq!(ship.top_view(), " A");
     }
 
     #[test]
     fn test_ship_put_empty_crate() {
         let mut ship = Ship::new();
-        ship.put_crate(2, Crate('A'));
-        ship.put_crate(3, Crate(' '));
+        ship.put_crate(2, Crate::from('A')).unwrap();
+        ship.put_crate(3, Crate::from(' ')).unwrap();
         assert_eq!(ship.top_view(), " A ");
-        ship.put_crate(3, Crate('B'));
+        ship.put_crate(3, Crate::from('B')).unwrap();
         assert_eq!(ship.top_view(), " AB");
     }
 
+    #[test]
+    fn test_ship_load_example() {
+        let mut ship = Ship::new();
+        ship.load(EXAMPLE.lines().take(4).collect()).unwrap();
+        assert_eq!(ship.labels, vec![1, 2, 3]);
+        assert_eq!(ship.top_view(), "NDP");
+        assert_eq!(ship.stacks[0], vec![Crate::from('Z'), Crate::from('N')]);
+    }
+
+    #[test]
+    fn test_ship_load_labels() {
+        let mut ship = Ship::new();
+        let layout = vec![
+            "                [X]",
+            "[A]     [B]     [Y]  ",
+            " 3   7   1   9   10",
+        ];
+        ship.load(layout.clone()).unwrap();
+        assert_eq!(ship.labels, vec![3, 7, 1, 9, 10]);
+        assert_eq!(ship.top_view(), "A B X");
+        assert_eq!(ship.pick_crate(10), Ok(Crate::from('X')));
+        assert_eq!(ship.pick_crate(1), Ok(Crate::from('B')));
+        assert_eq!(ship.pick_crate(2), Err(MoveError::InvalidStack(2)));
+        assert_eq!(
+            ship.put_crate(5, Crate::from('Z')),
+            Err(MoveError::InvalidStack(5))
+        );
+        assert_eq!(
+            ship.put_crate(0, Crate::NONE),
+            Err(MoveError::InvalidStack(0))
+        );
+        assert_eq!(
+            solution::<CrateMover9000>(&format!("{}\n\nmove 1 from 3 to 5", layout.join("\n"))),
+            Err(SolveError::FailedMove(SimulationError {
+                instruction: 1,
+                error: MoveError::InvalidStack(5),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_input_errors() {
+        assert_eq!(
+            solution::<CrateMover9000>("[A]\n 1   1\n\nmove 1 from 1 to 1"),
+            Err(SolveError::InvalidInput(InputError::InvalidDrawing(
+                ParseShipError::DuplicateLabel(1, 2)
+            )))
+        );
+        assert_eq!(
+            solution::<CrateMover9001>("[A]\n 1   2\n\nmove 1 from 1 to 2\nmove one from 2 to 1"),
+            Err(SolveError::InvalidInput(InputError::InvalidInstruction(
+                5,
+                ParseInstructionError::InvalidCount(
+                    "Invalid crates count in \"move one from 2 to 1\" instruction".to_owned()
+                )
+            )))
+        );
+        assert_eq!(
+            rope_solution("[A]\n 1   2\n\nmove 1 to 2", Order::Reversed)
+                .unwrap_err()
+                .to_string(),
+            "Line 4: Invalid instruction format \"move 1 to 2\""
+        );
+        let mut frames = Vec::new();
+        let err = animate::<CrateMover9000>("[A]\n", Playback::Record, &mut frames, &typed(&[]))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn test_ship_load_errors() {
+        let load = |layout: Vec<&str>| Ship::new().load(layout);
+        assert_eq!(load(vec![]), Err(ParseShipError::MissingFooter));
+        assert_eq!(load(vec!["[A]", "   "]), Err(ParseShipError::MissingFooter));
+        assert_eq!(
+            load(vec!["[A]", " 1   x "]),
+            Err(ParseShipError::InvalidLabel("x".to_owned(), 2))
+        );
+        assert_eq!(
+            load(vec!["[A]", " 1   1 "]),
+            Err(ParseShipError::DuplicateLabel(1, 2))
+        );
+        assert_eq!(
+            load(vec!["[A] [B", " 1   2 "]),
+            Err(ParseShipError::MalformedCrate(1, 5))
+        );
+        assert_eq!(
+            load(vec!["[A] (B)", " 1   2 "]),
+            Err(ParseShipError::UnexpectedCharacter('(', 1, 5))
+        );
+        assert_eq!(
+            load(vec!["[A] [B]", " 1 "]),
+            Err(ParseShipError::UnlabeledCrate(1, 5))
+        );
+        assert_eq!(
+            load(vec!["    [B]", "[A]", " 1   2 "]),
+            Err(ParseShipError::FloatingCrate(1, 5))
+        );
+        assert_eq!(
+            load(vec!["[A] [B", " 1   2 "]).unwrap_err().to_string(),
+            "Malformed crate on line 1, column 5, expected \"[X]\""
+        );
+    }
+
+    #[test]
+    fn test_ship_display_round_trip() {
+        let diagrams = puzzle_diagrams();
+        for diagram in &diagrams[1..] {
+            let mut ship = Ship::new();
+            ship.load(diagram.lines().collect()).unwrap();
+            assert_eq!(ship.to_string(), *diagram);
+        }
+
+        let mut ship = Ship::new();
+        let layout = vec!["[A]          [X]", "[B]  [C]     [Y]", " 3    10   9  1"];
+        ship.load(layout).unwrap();
+        let rendered = ship.to_string();
+        assert_eq!(rendered, "[A]         [X]\n[B] [C]     [Y]\n 3  10   9   1");
+        let mut reloaded = Ship::new();
+        reloaded.load(rendered.lines().collect()).unwrap();
+        assert_eq!(reloaded.labels, ship.labels);
+        assert_eq!(reloaded.stacks, ship.stacks);
+    }
+
+    #[test]
+    fn test_ship_display_after_every_move() {
+        let diagrams = puzzle_diagrams();
+        let mut frames = Vec::new();
+        simulate::<CrateMover9000>(EXAMPLE, |ship| frames.push(ship.to_string())).unwrap();
+        assert_eq!(frames, diagrams[1..5]);
+
+        frames.clear();
+        simulate::<CrateMover9001>(EXAMPLE, |ship| frames.push(ship.to_string())).unwrap();
+        assert_eq!(frames, diagrams[6..10]);
+    }
+
+    #[test]
+    fn test_move_errors() {
+        let drawing = "[A]\n[B] [C]\n 1   2   3\n\n";
+        let failing = |instructions: &str| {
+            let input = format!("{}{}", drawing, instructions);
+            (
+                solution::<CrateMover9000>(&input),
+                solution::<CrateMover9001>(&input),
+            )
+        };
+        let error = |instruction, error: MoveError| {
+            let failure = |error| Err(SimulationError { instruction, error }.into());
+            (failure(error.clone()), failure(error))
+        };
+
+        assert_eq!(
+            failing("move 1 from 0 to 2"),
+            error(1, MoveError::InvalidStack(0))
+        );
+        assert_eq!(
+            failing("move 1 from 1 to 2\nmove 1 from 2 to 4"),
+            error(2, MoveError::InvalidStack(4))
+        );
+        assert_eq!(
+            failing("move 1 from 3 to 1"),
+            error(1, MoveError::EmptyStack(3))
+        );
+        assert_eq!(
+            failing("move 1 from 2 to 1\nmove 2 from 2 to 3"),
+            error(2, MoveError::EmptyStack(2))
+        );
+        assert_eq!(
+            failing("move 3 from 1 to 3"),
+            error(1, MoveError::InsufficientCrates(1, 3, 2))
+        );
+        assert_eq!(
+            solution::<CrateMover9000>(&format!("{}move 3 from 1 to 3", drawing))
+                .unwrap_err()
+                .to_string(),
+            "Instruction 1: Cannot move 3 crates from stack 1, it only has 2"
+        );
+    }
+
+    #[test]
+    fn test_failed_move_leaves_ship_untouched() {
+        let mut ship = Ship::new();
+        ship.load(EXAMPLE.lines().take(4).collect()).unwrap();
+        let before = ship.to_string();
+        assert_eq!(
+            CrateMover9000::move_crates(&mut ship, 2, 4, 2),
+            Err(MoveError::InvalidStack(4))
+        );
+        assert_eq!(
+            CrateMover9000::move_crates(&mut ship, 3, 1, 2),
+            Err(MoveError::InsufficientCrates(3, 2, 1))
+        );
+        assert_eq!(ship.to_string(), before);
+    }
+
+    #[test]
+    fn test_loaded_ship_does_not_grow() {
+        let mut ship = Ship::new();
+        ship.load(vec!["[A]     [C]", " 1   2   3"]).unwrap();
+        let before = ship.to_string();
+        assert_eq!(
+            ship.put_crate(7, Crate::from('D')),
+            Err(MoveError::InvalidStack(7))
+        );
+        assert_eq!(ship.to_string(), before);
+        assert_eq!(ship.put_crate(2, Crate::from('B')), Ok(()));
+        assert_eq!(ship.to_string(), "[A] [B] [C]\n 1   2   3");
+    }
+
+    fn instructions(program: &str) -> Vec<Instruction> {
+        program.lines().map(|l| l.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_optimize_rules() {
+        let program = instructions(
+            "move 2 from 1 to 2\nmove 0 from 3 to 1\nmove 1 from 3 to 3\n\
+             move 1 from 3 to 4\nmove 3 from 1 to 2\nmove 4 from 2 to 1",
+        );
+        assert_eq!(
+            optimize::<CrateMover9000>(&program),
+            instructions("move 1 from 3 to 4\nmove 1 from 1 to 2")
+        );
+        assert_eq!(
+            optimize::<CrateMover9001>(&program),
+            instructions(
+                "move 2 from 1 to 2\nmove 1 from 3 to 4\n\
+                 move 3 from 1 to 2\nmove 4 from 2 to 1"
+            )
+        );
+
+        let program = instructions("move 2 from 1 to 2\nmove 1 from 3 to 4\nmove 2 from 2 to 1");
+        assert_eq!(
+            optimize::<CrateMover9001>(&program),
+            instructions("move 1 from 3 to 4")
+        );
+    }
+
+    #[test]
+    fn test_optimize_solution() {
+        fn check<T: CrateMover>(input: &str) {
+            let (ship, program) = parse_input(input).unwrap();
+            let optimized = optimize::<T>(&program);
+            assert!(optimized.len() <= program.len());
+            let (mut expected, mut actual) = (ship.clone(), ship);
+            execute::<T>(&mut expected, &program, |_| {}).unwrap();
+            execute::<T>(&mut actual, &optimized, |_| {}).unwrap();
+            assert_eq!(actual.stacks, expected.stacks);
+        }
+        check::<CrateMover9000>(EXAMPLE);
+        check::<CrateMover9000>(INPUT);
+        check::<CrateMover9001>(EXAMPLE);
+        check::<CrateMover9001>(INPUT);
+    }
+
+    fn ship() -> impl Strategy<Value = Ship> {
+        prop::collection::vec(
+            prop::collection::vec(prop::char::range('A', 'Z'), 0..6),
+            1..5,
+        )
+        .prop_map(|stacks| {
+            let mut ship = Ship::new();
+            for (i, stack) in stacks.into_iter().enumerate() {
+                ship.put_crate(i + 1, Crate::NONE).unwrap();
+                for cr in stack {
+                    ship.put_crate(i + 1, Crate::from(cr)).unwrap();
+                }
+            }
+            ship
+        })
+    }
+
+    // Random instructions with the ones that would fail on the ship left out, so that
+    // every generated program runs to completion.
+    fn program<T: CrateMover>(ship: &Ship) -> impl Strategy<Value = Vec<Instruction>> {
+        let ship = ship.clone();
+        let stacks = ship.labels.len();
+        prop::collection::vec((1..=stacks, 1..=stacks, 0usize..4), 0..30).prop_map(move |moves| {
+            let mut ship = ship.clone();
+            moves
+                .into_iter()
+                .map(|(from, to, count)| Instruction { from, to, count })
+                .filter(|i| T::move_crates(&mut ship, i.from, i.to, i.count).is_ok())
+                .collect()
+        })
+    }
+
+    fn ship_and_program<T: CrateMover>() -> impl Strategy<Value = (Ship, Vec<Instruction>)> {
+        ship().prop_flat_map(|ship| (Just(ship.clone()), program::<T>(&ship)))
+    }
+
+    fn check_optimized<T: CrateMover>(ship: &Ship, program: &[Instruction]) -> bool {
+        let (mut expected, mut actual) = (ship.clone(), ship.clone());
+        execute::<T>(&mut expected, program, |_| {}).unwrap();
+        execute::<T>(&mut actual, &optimize::<T>(program), |_| {}).unwrap();
+        actual.stacks == expected.stacks
+    }
+
+    proptest! {
+        #[test]
+        fn prop_optimize_9000(
+            (ship, program) in ship_and_program::<CrateMover9000>()
+        ) {
+            prop_assert!(check_optimized::<CrateMover9000>(&ship, &program));
+        }
+
+        #[test]
+        fn prop_optimize_9001(
+            (ship, program) in ship_and_program::<CrateMover9001>()
+        ) {
+            prop_assert!(check_optimized::<CrateMover9001>(&ship, &program));
+        }
+    }
+
+    #[test]
+    fn test_capped_crate_mover() {
+        assert_eq!(
+            solution::<CappedCrateMover<1>>(EXAMPLE),
+            Ok("CMZ".to_owned())
+        );
+        assert_eq!(
+            solution::<CappedCrateMover<2>>(EXAMPLE),
+            Ok("MCZ".to_owned())
+        );
+        assert_eq!(
+            solution::<CappedCrateMover<3>>(EXAMPLE),
+            Ok("MCD".to_owned())
+        );
+        assert_eq!(
+            solution::<CappedCrateMover<1>>(INPUT),
+            solution::<CrateMover9000>(INPUT)
+        );
+        assert_eq!(
+            solution::<CappedCrateMover<{ usize::MAX }>>(INPUT),
+            solution::<CrateMover9001>(INPUT)
+        );
+    }
+
+    #[test]
+    fn test_threshold_crate_mover() {
+        assert_eq!(
+            solution::<ThresholdCrateMover<0>>(EXAMPLE),
+            Ok("CMZ".to_owned())
+        );
+        assert_eq!(
+            solution::<ThresholdCrateMover<2>>(EXAMPLE),
+            Ok("MCZ".to_owned())
+        );
+        assert_eq!(
+            solution::<ThresholdCrateMover<3>>(EXAMPLE),
+            Ok("MCD".to_owned())
+        );
+        assert_eq!(
+            solution::<ThresholdCrateMover<0>>(INPUT),
+            solution::<CrateMover9000>(INPUT)
+        );
+    }
+
+    #[test]
+    fn test_height_limited_crate_mover() {
+        assert_eq!(
+            solution::<HeightLimitedCrateMover<CrateMover9001, 3>>(EXAMPLE),
+            Err(SolveError::FailedMove(SimulationError {
+                instruction: 2,
+                error: MoveError::StackTooHigh(3, 3),
+            }))
+        );
+        assert_eq!(
+            solution::<HeightLimitedCrateMover<CrateMover9001, 4>>(EXAMPLE),
+            Ok("MCD".to_owned())
+        );
+        assert_eq!(
+            solution::<HeightLimitedCrateMover<CrateMover9000, 4>>(EXAMPLE),
+            Ok("CMZ".to_owned())
+        );
+        assert_eq!(
+            solution::<HeightLimitedCrateMover<CrateMover9000, { usize::MAX }>>(INPUT),
+            solution::<CrateMover9000>(INPUT)
+        );
+    }
+
+    #[test]
+    fn test_timeline_steps() {
+        let (ship, program) = parse_input(EXAMPLE).unwrap();
+        let initial = ship.to_string();
+        let diagrams = puzzle_diagrams();
+        let mut timeline = Timeline::<CrateMover9000>::new(ship, program);
+
+        assert!(!timeline.step_backward());
+        assert_eq!(timeline.step_forward(), Ok(true));
+        assert_eq!(timeline.ship.to_string(), diagrams[1]);
+        assert!(timeline.step_backward());
+        assert_eq!(timeline.ship.to_string(), initial);
+
+        assert_eq!(timeline.seek(4).unwrap().top_view(), "CMZ");
+        assert_eq!(timeline.step_forward(), Ok(false));
+        assert_eq!(timeline.seek(2).unwrap().to_string(), diagrams[2]);
+        assert_eq!(timeline.seek(3).unwrap().to_string(), diagrams[3]);
+        assert_eq!(timeline.seek(0).unwrap().to_string(), initial);
+        assert_eq!(timeline.seek(10).unwrap().top_view(), "CMZ");
+        assert_eq!(timeline.position, 4);
+    }
+
+    #[test]
+    fn test_timeline_diff() {
+        let (ship, program) = parse_input(EXAMPLE).unwrap();
+        let mut timeline = Timeline::<CrateMover9001>::new(ship, program);
+        assert_eq!(
+            timeline.diff(0, 1),
+            Ok(vec![
+                StackChange {
+                    stack: 1,
+                    removed: vec![],
+                    added: vec![Crate::from('D')],
+                },
+                StackChange {
+                    stack: 2,
+                    removed: vec![Crate::from('D')],
+                    added: vec![],
+                },
+            ])
+        );
+        assert_eq!(
+            timeline.diff(4, 2),
+            Ok(vec![
+                StackChange {
+                    stack: 1,
+                    removed: vec![Crate::from('M')],
+                    added: vec![],
+                },
+                StackChange {
+                    stack: 2,
+                    removed: vec![Crate::from('C')],
+                    added: vec![Crate::from('M'), Crate::from('C')],
+                },
+            ])
+        );
+        assert_eq!(timeline.diff(3, 3), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_timeline_solution() {
+        let (ship, program) = parse_input(INPUT).unwrap();
+        let initial = ship.stacks.clone();
+        let mut expected = ship.clone();
+        execute::<CrateMover9001>(&mut expected, &program[..250], |_| {}).unwrap();
+        let mut timeline = Timeline::<CrateMover9001>::new(ship, program);
+        assert_eq!(timeline.seek(usize::MAX).unwrap().top_view(), "TPFFBDRJD");
+        assert_eq!(timeline.seek(250).unwrap().stacks, expected.stacks);
+        assert_eq!(timeline.seek(0).unwrap().stacks, initial);
+    }
+
+    #[test]
+    fn test_timeline_error() {
+        let (ship, _) = parse_input(EXAMPLE).unwrap();
+        let program = instructions("move 1 from 2 to 1\nmove 5 from 1 to 3");
+        let mut timeline = Timeline::<CrateMover9000>::new(ship, program);
+        assert_eq!(
+            timeline.seek(2).map(|ship| ship.top_view()),
+            Err(SimulationError {
+                instruction: 2,
+                error: MoveError::InsufficientCrates(1, 5, 3),
+            })
+        );
+        assert_eq!(timeline.position, 1);
+    }
+
+    #[test]
+    fn test_provenance_example() {
+        let (ship, program) = parse_input(EXAMPLE).unwrap();
+        let mut timeline = Timeline::<CrateMover9000>::new(ship, program);
+        let report: Vec<String> = timeline
+            .provenance()
+            .unwrap()
+            .iter()
+            .map(Journey::to_string)
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                "[Z] from stack 1, height 1: stack 3 after move 2",
+                "[N] from stack 1, height 2: stack 3 after move 2",
+                "[M] from stack 2, height 1: stack 1 after move 3, stack 2 after move 4",
+                "[C] from stack 2, height 2: stack 1 after move 3",
+                "[D] from stack 2, height 3: stack 1 after move 1, stack 3 after move 2",
+                "[P] from stack 3, height 1: never moved",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_provenance_built_ship() {
+        let mut ship = Ship::new();
+        for (stack, marks) in ["AA", "A"].iter().enumerate() {
+            for mark in marks.chars() {
+                ship.put_crate(stack + 1, Crate::from(mark)).unwrap();
+            }
+        }
+        assert_eq!(
+            ship.stacks[0][1].1,
+            Origin {
+                stack: 1,
+                height: 2
+            }
+        );
+        let program = instructions("move 2 from 1 to 2");
+        let mut timeline = Timeline::<CrateMover9000>::new(ship, program);
+        let report: Vec<String> = timeline
+            .provenance()
+            .unwrap()
+            .iter()
+            .map(Journey::to_string)
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                "[A] from stack 1, height 1: stack 2 after move 1",
+                "[A] from stack 1, height 2: stack 2 after move 1",
+                "[A] from stack 2, height 1: never moved",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_provenance_crates_placed_after_move() {
+        let mut ship = Ship::new();
+        ship.put_crate(1, Crate::from('A')).unwrap();
+        ship.put_crate(2, Crate::NONE).unwrap();
+        CrateMover9000::move_crates(&mut ship, 1, 2, 1).unwrap();
+        ship.put_crate(1, Crate::from('B')).unwrap();
+        assert_eq!(ship.stacks[0][0].1, ship.stacks[1][0].1);
+        assert_ne!(ship.stacks[0][0].2, ship.stacks[1][0].2);
+
+        let program = instructions("move 1 from 1 to 2");
+        let mut timeline = Timeline::<CrateMover9000>::new(ship, program);
+        let report: Vec<String> = timeline
+            .provenance()
+            .unwrap()
+            .iter()
+            .map(Journey::to_string)
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                "[A] from stack 1, height 1: never moved",
+                "[B] from stack 1, height 1: stack 2 after move 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_provenance_solution() {
+        let (ship, program) = parse_input(INPUT).unwrap();
+        let crates = ship.stacks.iter().map(Vec::len).sum::<usize>();
+        let mut timeline = Timeline::<CrateMover9001>::new(ship, program);
+        let journeys = timeline.provenance().unwrap();
+        assert_eq!(journeys.len(), crates);
+        for (stack, label) in timeline.ship.stacks.iter().zip(&timeline.ship.labels) {
+            if let Some(top) = stack.last() {
+                let journey = journeys.iter().find(|j| j.id == top.2).unwrap();
+                let last = journey.moves.last().map_or(journey.origin.stack, |m| m.1);
+                assert_eq!(last, *label);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rope_solution() {
+        assert_eq!(
+            rope_solution(EXAMPLE, Order::Reversed),
+            Ok("CMZ".to_owned())
+        );
+        assert_eq!(
+            rope_solution(INPUT, Order::Reversed),
+            Ok("LBLVVTVLP".to_owned())
+        );
+        assert_eq!(
+            rope_solution(EXAMPLE, Order::Preserved),
+            Ok("MCD".to_owned())
+        );
+        assert_eq!(
+            rope_solution(INPUT, Order::Preserved),
+            Ok("TPFFBDRJD".to_owned())
+        );
+        assert_eq!(
+            rope_solution(
+                "[A]\n 1   2\n\nmove 1 from 1 to 2\nmove 1 from 1 to 2",
+                Order::Reversed
+            ),
+            Err(SolveError::FailedMove(SimulationError {
+                instruction: 2,
+                error: MoveError::EmptyStack(1),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_rope_stress() {
+        const CRATES: usize = 1_000_000;
+        let stack: Vec<Crate> = (0..CRATES)
+            .map(|height| {
+                let mark = char::from(b'A' + (height % 26) as u8);
+                Crate(mark, Origin { stack: 1, height }, height + 1)
+            })
+            .collect();
+        let ship = Ship {
+            stacks: vec![stack, Vec::new(), Vec::new()],
+            labels: vec![1, 2, 3],
+            fixed: true,
+            last_id: CRATES,
+        };
+        let mut rope = RopeShip::from(&ship);
+        for i in 0..100_000 {
+            let (to, count) = (i % 2 + 2, i * 7919 % CRATES + 1);
+            rope.move_crates(1, to, count, Order::Reversed).unwrap();
+            rope.move_crates(to, 1, count, Order::Reversed).unwrap();
+            rope.move_crates(1, to, count, Order::Preserved).unwrap();
+            rope.move_crates(to, 1, count, Order::Preserved).unwrap();
+        }
+        rope.move_crates(1, 3, CRATES, Order::Preserved).unwrap();
+        rope.move_crates(3, 2, CRATES, Order::Reversed).unwrap();
+        let stacks = rope.stacks();
+        assert_eq!(stacks[1].first().unwrap().1.height, CRATES - 1);
+        assert_eq!(stacks[1].last().unwrap().1.height, 0);
+    }
+
+    #[test]
+    fn test_rope_stress_interleaved() {
+        const CRATES: usize = 1_000_000;
+        let stack: Vec<Crate> = (0..CRATES)
+            .map(|height| Crate('A', Origin { stack: 1, height }, height + 1))
+            .collect();
+        let ship = Ship {
+            stacks: vec![stack, Vec::new(), Vec::new()],
+            labels: vec![1, 2, 3],
+            fixed: true,
+            last_id: CRATES,
+        };
+        let mut rope = RopeShip::from(&ship);
+        // Single crates dealt out to alternating stacks leave every stack in tiny runs.
+        for i in 0..200_000 {
+            let order = [Order::Reversed, Order::Preserved][i % 2];
+            rope.move_crates(1, i % 2 + 2, 1, order).unwrap();
+        }
+        for i in 0..200_000 {
+            let (from, to) = [(2, 3), (3, 1), (1, 2)][i % 3];
+            let order = [Order::Reversed, Order::Preserved][i / 3 % 2];
+            let count = rope.height(rope.stack_index(from).unwrap());
+            rope.move_crates(from, to, count, order).unwrap();
+        }
+        let stacks = rope.stacks();
+        assert_eq!(stacks.iter().map(Vec::len).sum::<usize>(), CRATES);
+        let mut ids: Vec<usize> = stacks.iter().flatten().map(|cr| cr.2).collect();
+        ids.sort_unstable();
+        assert!(ids.iter().copied().eq(1..=CRATES));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_rope_matches_9000(
+            (ship, program) in ship_and_program::<CrateMover9000>()
+        ) {
+            let mut rope = RopeShip::from(&ship);
+            let mut expected = ship;
+            execute::<CrateMover9000>(&mut expected, &program, |_| {}).unwrap();
+            for i in program {
+                rope.move_crates(i.from, i.to, i.count, Order::Reversed).unwrap();
+            }
+            prop_assert_eq!(rope.stacks(), expected.stacks);
+        }
+
+        #[test]
+        fn prop_rope_matches_9001(
+            (ship, program) in ship_and_program::<CrateMover9001>()
+        ) {
+            let mut rope = RopeShip::from(&ship);
+            let mut expected = ship;
+            execute::<CrateMover9001>(&mut expected, &program, |_| {}).unwrap();
+            for i in program {
+                rope.move_crates(i.from, i.to, i.count, Order::Preserved).unwrap();
+            }
+            prop_assert_eq!(rope.stacks(), expected.stacks);
+        }
+
+        #[test]
+        fn prop_provenance_traces_every_crate(
+            (ship, program) in ship_and_program::<CrateMover9000>()
+        ) {
+            let crates = ship.stacks.iter().map(Vec::len).sum::<usize>();
+            let mut timeline = Timeline::<CrateMover9000>::new(ship, program);
+            let journeys = timeline.provenance().unwrap();
+            prop_assert_eq!(journeys.len(), crates);
+            for (stack, label) in timeline.ship.stacks.iter().zip(&timeline.ship.labels) {
+                for cr in stack {
+                    let journey = journeys.iter().find(|j| j.id == cr.2).unwrap();
+                    let last = journey.moves.last().map_or(journey.origin.stack, |m| m.1);
+                    prop_assert_eq!(last, *label);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_arrangement() {
+        let (ship, _) = parse_input(EXAMPLE).unwrap();
+        let mut after_first_move = ship.clone();
+        CrateMover9000::move_crates(&mut after_first_move, 2, 1, 1).unwrap();
+        let goal = Goal::Arrangement(after_first_move.stacks);
+        assert_eq!(
+            plan::<CrateMover9000>(&ship, &goal, 3),
+            Some(instructions("move 1 from 2 to 1"))
+        );
+
+        let target = simulate::<CrateMover9001>(EXAMPLE, |_| {}).unwrap();
+        let goal = Goal::Arrangement(target.stacks.clone());
+        let found = plan::<CrateMover9001>(&ship, &goal, 4).unwrap();
+        assert_eq!(found.len(), 4);
+        let mut actual = ship.clone();
+        execute::<CrateMover9001>(&mut actual, &found, |_| {}).unwrap();
+        assert_eq!(actual.stacks, target.stacks);
+        assert_eq!(plan::<CrateMover9001>(&ship, &goal, 3), None);
+    }
+
+    #[test]
+    fn test_plan_top_view() {
+        let (ship, _) = parse_input(EXAMPLE).unwrap();
+        let goal = Goal::TopView("CMZ".to_owned());
+        let found = plan::<CrateMover9000>(&ship, &goal, 4).unwrap();
+        let mut actual = ship.clone();
+        execute::<CrateMover9000>(&mut actual, &found, |_| {}).unwrap();
+        assert_eq!(actual.top_view(), "CMZ");
+        assert!(found.len() <= 4);
+
+        assert_eq!(
+            plan::<CrateMover9000>(&ship, &Goal::TopView("NDP".to_owned()), 0),
+            Some(vec![])
+        );
+        assert_eq!(
+            plan::<CrateMover9000>(&ship, &Goal::TopView("XYZ".to_owned()), 3),
+            None
+        );
+    }
+
+    #[test]
+    fn test_animate_record() {
+        let mut frames = Vec::new();
+        animate::<CrateMover9000>(EXAMPLE, Playback::Record, &mut frames, &typed(&[])).unwrap();
+        let frames = String::from_utf8(frames).unwrap();
+        let frames: Vec<&str> = frames.split("\n\n").filter(|f| !f.is_empty()).collect();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(
+            frames[0],
+            format!("Initial drawing\n{}", puzzle_diagrams()[5])
+        );
+        assert_eq!(
+            frames[2],
+            [
+                "Move 2/4: move 3 from 1 to 3",
+                "        {Z}",
+                "        {N}",
+                "    [C] {D}",
+                "    [M] [P]",
+                " 1   2   3",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_animate_step_controls() {
+        let mut frames = Vec::new();
+        animate::<CrateMover9001>(
+            EXAMPLE,
+            Playback::Step(Duration::ZERO),
+            &mut frames,
+            &typed(&["\n", "q\n"]),
+        )
+        .unwrap();
+        let frames = String::from_utf8(frames).unwrap();
+        assert_eq!(frames.matches("\x1b[2J").count(), 3);
+        assert!(frames.contains("Move 2/4: move 3 from 1 to 3"));
+        assert!(!frames.contains("Move 3/4"));
+        assert!(frames.contains("\x1b[1;33m[D]\x1b[0m"));
+
+        let mut frames = Vec::new();
+        animate::<CrateMover9001>(
+            EXAMPLE,
+            Playback::Step(Duration::ZERO),
+            &mut frames,
+            &typed(&["c\n"]),
+        )
+        .unwrap();
+        let frames = String::from_utf8(frames).unwrap();
+        assert!(frames.contains("Move 4/4: move 1 from 1 to 2"));
+        assert_eq!(frames.matches("[Enter] next move").count(), 1);
+    }
+
+    #[test]
+    fn test_animate_pause_while_playing() {
+        let mut frames = Vec::new();
+        animate::<CrateMover9000>(
+            EXAMPLE,
+            Playback::Play(Duration::ZERO),
+            &mut frames,
+            &typed(&["\n", "\n", "q\n"]),
+        )
+        .unwrap();
+        let frames = String::from_utf8(frames).unwrap();
+        assert!(frames.contains("Move 2/4: move 3 from 1 to 3"));
+        assert!(!frames.contains("Move 3/4"));
+        assert_eq!(frames.matches("[Enter] next move").count(), 2);
+
+        let mut frames = Vec::new();
+        animate::<CrateMover9000>(
+            EXAMPLE,
+            Playback::Play(Duration::ZERO),
+            &mut frames,
+            &typed(&["q\n"]),
+        )
+        .unwrap();
+        let frames = String::from_utf8(frames).unwrap();
+        assert!(frames.contains("Move 1/4"));
+        assert!(!frames.contains("Move 2/4"));
+    }
+
+    // Animates the rearrangement in the terminal. The binary has no entry point for it, as
+    // every day only lives in its tests, so it runs as an ignored test instead:
+    //
+    //   cargo test day05::tests::visualize -- --ignored --nocapture
+    //
+    // DAY05_INPUT=input switches to the puzzle input, DAY05_MOVER=9001 to the second
+    // crane, DAY05_DELAY_MS sets the speed, DAY05_STEP=1 waits for a command after every
+    // move, and DAY05_FRAMES=<path> records plain frames to a file instead.
+    #[test]
+    #[ignore]
+    fn visualize() {
+        let input = match env::var("DAY05_INPUT").as_deref() {
+            Ok("input") => INPUT,
+            _ => EXAMPLE,
+        };
+        let delay = env::var("DAY05_DELAY_MS")
+            .ok()
+            .and_then(|ms| ms.parse().ok())
+            .map_or(Duration::from_millis(500), Duration::from_millis);
+        let (playback, mut out): (Playback, Box<dyn Write>) = match env::var("DAY05_FRAMES") {
+            Ok(path) => (
+                Playback::Record,
+                Box::new(BufWriter::new(File::create(path).unwrap())),
+            ),
+            Err(_) if env::var("DAY05_STEP").is_ok() => {
+                (Playback::Step(delay), Box::new(io::stdout()))
+            }
+            Err(_) => (Playback::Play(delay), Box::new(io::stdout())),
+        };
+        let (sender, controls) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                if sender.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+        match env::var("DAY05_MOVER").as_deref() {
+            Ok("9001") => animate::<CrateMover9001>(input, playback, &mut out, &controls),
+            _ => animate::<CrateMover9000>(input, playback, &mut out, &controls),
+        }
+        .unwrap();
+    }
+
     #[test]
     fn test_part1_example() {
-        assert_eq!(solution::<CrateMover9000>(EXAMPLE), "CMZ");
+        assert_eq!(solution::<CrateMover9000>(EXAMPLE), Ok("CMZ".to_owned()));
     }
 
     #[test]
     fn test_part1_solution() {
-        assert_eq!(solution::<CrateMover9000>(INPUT), "LBLVVTVLP");
+        assert_eq!(
+            solution::<CrateMover9000>(INPUT),
+            Ok("LBLVVTVLP".to_owned())
+        );
     }
 
     #[test]
     fn test_part2_example() {
-        assert_eq!(solution::<CrateMover9001>(EXAMPLE), "MCD");
+        assert_eq!(solution::<CrateMover9001>(EXAMPLE), Ok("MCD".to_owned()));
     }
 
     #[test]
     fn test_part2_solution() {
-        assert_eq!(solution::<CrateMover9001>(INPUT), "TPFFBDRJD");
+        assert_eq!(
+            solution::<CrateMover9001>(INPUT),
+            Ok("TPFFBDRJD".to_owned())
+        );
     }
 
     const EXAMPLE: &str = include_str!("example.txt");
     const INPUT: &str = include_str!("input.txt");
+    const README: &str = include_str!("README.md");
 }
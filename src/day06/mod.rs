@@ -1,77 +1,836 @@
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashSet, VecDeque};
+    use std::fmt;
+    use std::fmt::Display;
+    use std::io;
+    use std::io::Read;
+    use std::time::Instant;
 
-    fn solution(input: &str, marker_size: usize) -> usize {
-        input
-            .chars()
-            .collect::<Vec<char>>()
+    use proptest::prelude::*;
+
+    #[derive(Debug, PartialEq)]
+    enum MarkerError {
+        ZeroSize,
+        SizeExceedsSignal(usize, usize),
+        NotFound(usize),
+        NonAscii(usize),
+        ZeroChunkSize,
+    }
+
+    impl Display for MarkerError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                MarkerError::ZeroSize => write!(f, "The marker size must be at least 1"),
+                MarkerError::SizeExceedsSignal(size, len) => write!(
+                    f,
+                    "The marker size {} is larger than the signal of length {}",
+                    size, len
+                ),
+                MarkerError::NotFound(size) => {
+                    write!(f, "The signal has no marker of size {}", size)
+                }
+                MarkerError::NonAscii(offset) => {
+                    write!(f, "The signal has a non-ASCII character at byte {}", offset)
+                }
+                MarkerError::ZeroChunkSize => write!(f, "The chunk size must be at least 1"),
+            }
+        }
+    }
+
+    fn check_marker_size(marker_size: usize, signal_len: usize) -> Result<(), MarkerError> {
+        match marker_size {
+            0 => Err(MarkerError::ZeroSize),
+            _ if marker_size > signal_len => {
+                Err(MarkerError::SizeExceedsSignal(marker_size, signal_len))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // The fast detectors work on bytes, which only line up with characters in ASCII.
+    fn check_ascii(signal: &str) -> Result<(), MarkerError> {
+        match signal.bytes().position(|b| !b.is_ascii()) {
+            Some(offset) => Err(MarkerError::NonAscii(offset)),
+            None => Ok(()),
+        }
+    }
+
+    fn solution(input: &str, marker_size: usize) -> Result<usize, MarkerError> {
+        let chars: Vec<char> = input.chars().collect();
+        check_marker_size(marker_size, chars.len())?;
+        chars
             .windows(marker_size)
-            .enumerate()
-            .find(|(_, group)| HashSet::<&char>::from_iter(*group).len() == marker_size)
-            .unwrap()
-            .0
-            + marker_size
+            .position(|group| HashSet::<&char>::from_iter(group).len() == marker_size)
+            .map(|start| start + marker_size)
+            .ok_or(MarkerError::NotFound(marker_size))
+    }
+
+    // Tracks the longest run of distinct bytes ending at the latest byte, remembering where
+    // every byte was last seen: the run always starts right after the latest repeat.
+    struct DistinctRun {
+        last_seen: [Option<usize>; 256],
+        start: usize,
+        position: usize,
+    }
+
+    impl DistinctRun {
+        fn new() -> Self {
+            DistinctRun {
+                last_seen: [None; 256],
+                start: 0,
+                position: 0,
+            }
+        }
+
+        fn push(&mut self, b: u8) -> usize {
+            if let Some(seen) = self.last_seen[b as usize] {
+                self.start = self.start.max(seen + 1);
+            }
+            self.last_seen[b as usize] = Some(self.position);
+            self.position += 1;
+            self.position - self.start
+        }
+    }
+
+    // The first time the run of distinct bytes reaches `marker_size`, the marker ends at
+    // the current byte. All state lives in the scanner, so the signal can arrive in any
+    // number of pieces.
+    struct MarkerScanner {
+        marker_size: usize,
+        run: DistinctRun,
+        found: Option<usize>,
+    }
+
+    impl MarkerScanner {
+        fn new(marker_size: usize) -> Self {
+            MarkerScanner {
+                marker_size,
+                run: DistinctRun::new(),
+                found: None,
+            }
+        }
+
+        fn feed(&mut self, bytes: &[u8]) -> Option<usize> {
+            if self.found.is_some() {
+                return None;
+            }
+            for &b in bytes {
+                if self.run.push(b) == self.marker_size {
+                    self.found = Some(self.run.position);
+                    return self.found;
+                }
+            }
+            None
+        }
+    }
+
+    // Lengths of the runs of distinct bytes ending at every position, collected in one
+    // pass. A window of any size up to the run length ending at some byte is a marker, so
+    // every other query is a scan over these lengths.
+    struct SignalProfile {
+        runs: Vec<usize>,
+    }
+
+    impl SignalProfile {
+        fn new(signal: &str) -> Result<Self, MarkerError> {
+            check_ascii(signal)?;
+            let mut run = DistinctRun::new();
+            Ok(SignalProfile {
+                runs: signal.bytes().map(|b| run.push(b)).collect(),
+            })
+        }
+
+        fn markers(&self, marker_size: usize) -> impl Iterator<Item = usize> + '_ {
+            self.runs
+                .iter()
+                .enumerate()
+                .filter(move |&(_, &run)| marker_size > 0 && run >= marker_size)
+                .map(|(i, _)| i + 1)
+        }
+
+        // The earliest longest run as its starting offset and length.
+        fn longest_run(&self) -> Option<(usize, usize)> {
+            self.runs
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|&(_, &run)| run)
+                .map(|(i, &run)| (i + 1 - run, run))
+        }
+
+        fn smallest_missing_marker(&self) -> usize {
+            self.longest_run().map_or(0, |(_, len)| len) + 1
+        }
+    }
+
+    fn find_marker(input: &str, marker_size: usize) -> Result<usize, MarkerError> {
+        check_ascii(input)?;
+        check_marker_size(marker_size, input.len())?;
+        MarkerScanner::new(marker_size)
+            .feed(input.as_bytes())
+            .ok_or(MarkerError::NotFound(marker_size))
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Frame<'a> {
+        start: usize,
+        payload: &'a str,
+    }
+
+    // Splits a signal into frames: everything before the first marker is dropped, and each
+    // frame starts right after a marker and runs up to the next one. The next marker is
+    // looked for from scratch after the previous one, so markers never overlap.
+    struct Frames<'a> {
+        signal: &'a str,
+        marker_size: usize,
+        start: Option<usize>,
+    }
+
+    impl<'a> Frames<'a> {
+        fn new(signal: &'a str, marker_size: usize) -> Result<Self, MarkerError> {
+            check_ascii(signal)?;
+            check_marker_size(marker_size, signal.len())?;
+            let start = MarkerScanner::new(marker_size).feed(signal.as_bytes());
+            Ok(Frames {
+                signal,
+                marker_size,
+                start,
+            })
+        }
+    }
+
+    impl<'a> Iterator for Frames<'a> {
+        type Item = Frame<'a>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let start = self.start?;
+            let rest = &self.signal[start..];
+            self.start = MarkerScanner::new(self.marker_size)
+                .feed(rest.as_bytes())
+                .map(|end| start + end);
+            let end = self
+                .start
+                .map_or(self.signal.len(), |next| next - self.marker_size);
+            Some(Frame {
+                start,
+                payload: &self.signal[start..end],
+            })
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct FramingStats {
+        frames: usize,
+        average_distance: Option<f64>,
+        average_payload: f64,
+    }
+
+    // The distance between markers is measured from the end of one to the end of the next,
+    // so it only exists once there are at least two frames.
+    fn framing_stats(frames: &[Frame]) -> Option<FramingStats> {
+        let (first, last) = (frames.first()?, frames.last()?);
+        let payload: usize = frames.iter().map(|frame| frame.payload.len()).sum();
+        Some(FramingStats {
+            frames: frames.len(),
+            average_distance: (frames.len() > 1)
+                .then(|| (last.start - first.start) as f64 / (frames.len() - 1) as f64),
+            average_payload: payload as f64 / frames.len() as f64,
+        })
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Marker {
+        size: usize,
+        offset: usize,
+    }
+
+    // Reads the signal in chunks and yields every requested marker as soon as the chunk
+    // completing it arrives. Reading stops once all markers are found, so the source may
+    // never end. Offsets count bytes, as the source is read as raw bytes.
+    struct MarkerStream<R> {
+        reader: R,
+        scanners: Vec<MarkerScanner>,
+        chunk: Vec<u8>,
+        found: VecDeque<Marker>,
+    }
+
+    impl<R: Read> MarkerStream<R> {
+        // A zero-sized marker could never be found, and looking for one would read an
+        // endless source forever. An empty chunk reads nothing, which looks just like the
+        // end of the source.
+        fn new(reader: R, marker_sizes: &[usize], chunk_size: usize) -> Result<Self, MarkerError> {
+            if marker_sizes.contains(&0) {
+                return Err(MarkerError::ZeroSize);
+            }
+            if chunk_size == 0 {
+                return Err(MarkerError::ZeroChunkSize);
+            }
+            Ok(MarkerStream {
+                reader,
+                scanners: marker_sizes
+                    .iter()
+                    .map(|&size| MarkerScanner::new(size))
+                    .collect(),
+                chunk: vec![0; chunk_size],
+                found: VecDeque::new(),
+            })
+        }
+    }
+
+    impl<R: Read> Iterator for MarkerStream<R> {
+        type Item = io::Result<Marker>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                if let Some(marker) = self.found.pop_front() {
+                    return Some(Ok(marker));
+                }
+                if self.scanners.iter().all(|scanner| scanner.found.is_some()) {
+                    return None;
+                }
+                let read = match self.reader.read(&mut self.chunk) {
+                    Ok(0) => return None,
+                    Ok(read) => read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Some(Err(err)),
+                };
+                let mut found: Vec<Marker> = self
+                    .scanners
+                    .iter_mut()
+                    .filter_map(|scanner| {
+                        let offset = scanner.feed(&self.chunk[..read])?;
+                        Some(Marker {
+                            size: scanner.marker_size,
+                            offset,
+                        })
+                    })
+                    .collect();
+                found.sort_by_key(|marker| marker.offset);
+                self.found.extend(found);
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum PlantError {
+        ZeroSize,
+        AlphabetTooSmall(usize, usize),
+        PositionTooEarly(usize, usize),
+        PositionBeyondSignal(usize, usize),
+        UnavoidableMarker(usize),
+        NonAsciiAlphabet(char),
+    }
+
+    impl Display for PlantError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                PlantError::ZeroSize => write!(f, "The marker size must be at least 1"),
+                PlantError::AlphabetTooSmall(alphabet, size) => write!(
+                    f,
+                    "An alphabet of {} characters cannot hold a marker of size {}",
+                    alphabet, size
+                ),
+                PlantError::PositionTooEarly(position, size) => write!(
+                    f,
+                    "A marker of size {} cannot end at position {}",
+                    size, position
+                ),
+                PlantError::PositionBeyondSignal(position, len) => write!(
+                    f,
+                    "Position {} is past the end of a signal of length {}",
+                    position, len
+                ),
+                PlantError::UnavoidableMarker(position) => write!(
+                    f,
+                    "Every character is a marker of size 1, so none can end at position {}",
+                    position
+                ),
+                PlantError::NonAsciiAlphabet(c) => write!(
+                    f,
+                    "The alphabet has the non-ASCII character {:?}, signals must be ASCII",
+                    c
+                ),
+            }
+        }
+    }
+
+    // Produces random signals over an alphabet from a seed, so a failing case can be
+    // replayed. Uses xorshift64, which is plenty for test data.
+    struct SignalGenerator {
+        alphabet: Vec<u8>,
+        state: u64,
+    }
+
+    impl SignalGenerator {
+        // Repeated characters count once, so the alphabet size is the number of distinct
+        // characters a marker can use.
+        fn new(alphabet: &str, seed: u64) -> Result<Self, PlantError> {
+            if let Some(c) = alphabet.chars().find(|c| !c.is_ascii()) {
+                return Err(PlantError::NonAsciiAlphabet(c));
+            }
+            let mut distinct: Vec<u8> = Vec::new();
+            for b in alphabet.bytes() {
+                if !distinct.contains(&b) {
+                    distinct.push(b);
+                }
+            }
+            Ok(SignalGenerator {
+                alphabet: distinct,
+                // xorshift never leaves the all-zero state.
+                state: seed.max(1),
+            })
+        }
+
+        fn below(&mut self, bound: usize) -> usize {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            (self.state % bound as u64) as usize
+        }
+
+        fn pick(&mut self, from: &[u8]) -> u8 {
+            from[self.below(from.len())]
+        }
+
+        // A signal of `len` characters whose first marker of `marker_size` ends at
+        // `position`. Every character before the marker is picked so the run of distinct
+        // characters ending there stays shorter than the marker, and the last of them
+        // reappears as the first marker character, which breaks every window straddling
+        // the two. Characters after the marker are arbitrary.
+        fn planted(
+            &mut self,
+            marker_size: usize,
+            position: usize,
+            len: usize,
+        ) -> Result<String, PlantError> {
+            if marker_size == 0 {
+                return Err(PlantError::ZeroSize);
+            }
+            if marker_size > self.alphabet.len() {
+                return Err(PlantError::AlphabetTooSmall(
+                    self.alphabet.len(),
+                    marker_size,
+                ));
+            }
+            if position < marker_size {
+                return Err(PlantError::PositionTooEarly(position, marker_size));
+            }
+            if position > len {
+                return Err(PlantError::PositionBeyondSignal(position, len));
+            }
+            if marker_size == 1 && position > 1 {
+                return Err(PlantError::UnavoidableMarker(position));
+            }
+            let alphabet = self.alphabet.clone();
+            let mut signal: Vec<u8> = Vec::with_capacity(len);
+            let mut run: Vec<u8> = Vec::new();
+            while signal.len() < position - marker_size {
+                let next = if run.len() + 1 < marker_size {
+                    self.pick(&alphabet)
+                } else {
+                    self.pick(&run)
+                };
+                if let Some(repeat) = run.iter().position(|&b| b == next) {
+                    run.drain(..=repeat);
+                }
+                run.push(next);
+                signal.push(next);
+            }
+            let mut marker: Vec<u8> = signal.last().copied().into_iter().collect();
+            while marker.len() < marker_size {
+                let next = self.pick(&alphabet);
+                if !marker.contains(&next) {
+                    marker.push(next);
+                }
+            }
+            signal.extend(marker);
+            while signal.len() < len {
+                signal.push(self.pick(&alphabet));
+            }
+            Ok(signal.into_iter().map(char::from).collect())
+        }
     }
 
     #[test]
     fn test_part1_example() {
-        assert_eq!(solution(EXAMPLE, 4), 7);
+        assert_eq!(solution(EXAMPLE, 4), Ok(7));
     }
 
     #[test]
     fn test_part1_example2() {
-        assert_eq!(solution("bvwbjplbgvbhsrlpgdmjqwftvncz", 4), 5);
+        assert_eq!(solution("bvwbjplbgvbhsrlpgdmjqwftvncz", 4), Ok(5));
     }
 
     #[test]
     fn test_part1_example3() {
-        assert_eq!(solution("nppdvjthqldpwncqszvftbrmjlhg", 4), 6);
+        assert_eq!(solution("nppdvjthqldpwncqszvftbrmjlhg", 4), Ok(6));
     }
 
     #[test]
     fn test_part1_example4() {
-        assert_eq!(solution("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4), 10);
+        assert_eq!(solution("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4), Ok(10));
     }
 
     #[test]
     fn test_part1_example5() {
-        assert_eq!(solution("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4), 11);
+        assert_eq!(solution("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4), Ok(11));
     }
 
     #[test]
     fn test_part1_solution() {
-        assert_eq!(solution(INPUT, 4), 1702);
+        assert_eq!(solution(INPUT, 4), Ok(1702));
     }
 
     #[test]
     fn test_part2_example() {
-        assert_eq!(solution(EXAMPLE, 14), 19);
+        assert_eq!(solution(EXAMPLE, 14), Ok(19));
     }
 
     #[test]
     fn test_part2_example2() {
-        assert_eq!(solution("bvwbjplbgvbhsrlpgdmjqwftvncz", 14), 23);
+        assert_eq!(solution("bvwbjplbgvbhsrlpgdmjqwftvncz", 14), Ok(23));
     }
 
     #[test]
     fn test_part2_example3() {
-        assert_eq!(solution("nppdvjthqldpwncqszvftbrmjlhg", 14), 23);
+        assert_eq!(solution("nppdvjthqldpwncqszvftbrmjlhg", 14), Ok(23));
     }
 
     #[test]
     fn test_part2_example4() {
-        assert_eq!(solution("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 14), 29);
+        assert_eq!(solution("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 14), Ok(29));
     }
 
     #[test]
     fn test_part2_example5() {
-        assert_eq!(solution("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14), 26);
+        assert_eq!(solution("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14), Ok(26));
     }
 
     #[test]
     fn test_part2_solution() {
-        assert_eq!(solution(INPUT, 14), 3559);
+        assert_eq!(solution(INPUT, 14), Ok(3559));
+    }
+
+    #[test]
+    fn test_marker_errors() {
+        for detect in [solution, find_marker] {
+            assert_eq!(detect("abcd", 0), Err(MarkerError::ZeroSize));
+            assert_eq!(detect("", 1), Err(MarkerError::SizeExceedsSignal(1, 0)));
+            assert_eq!(detect("abc", 4), Err(MarkerError::SizeExceedsSignal(4, 3)));
+            assert_eq!(detect("abcabcabc", 4), Err(MarkerError::NotFound(4)));
+            assert_eq!(detect("abcd", 4), Ok(4));
+            assert_eq!(detect("a", 1), Ok(1));
+        }
+        assert_eq!(
+            solution("abc", 4).unwrap_err().to_string(),
+            "The marker size 4 is larger than the signal of length 3"
+        );
+        assert!(matches!(
+            MarkerStream::new(io::empty(), &[4, 0], 16),
+            Err(MarkerError::ZeroSize)
+        ));
+        assert!(matches!(
+            MarkerStream::new(io::repeat(b'a'), &[4], 0),
+            Err(MarkerError::ZeroChunkSize)
+        ));
+        assert_eq!(
+            solution("\u{e9}", 2),
+            Err(MarkerError::SizeExceedsSignal(2, 1))
+        );
+        assert_eq!(find_marker("\u{e9}", 2), Err(MarkerError::NonAscii(0)));
+        assert_eq!(solution("ab\u{e9}c", 4), Ok(4));
+        assert_eq!(
+            find_marker("ab\u{e9}c", 4).unwrap_err().to_string(),
+            "The signal has a non-ASCII character at byte 2"
+        );
+    }
+
+    #[test]
+    fn test_find_marker_examples() {
+        let examples = [
+            (EXAMPLE, 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (signal, packet, message) in examples {
+            assert_eq!(find_marker(signal, 4), Ok(packet));
+            assert_eq!(find_marker(signal, 14), Ok(message));
+        }
+    }
+
+    #[test]
+    fn test_find_marker_solution() {
+        for marker_size in 1..=14 {
+            assert_eq!(
+                find_marker(INPUT, marker_size),
+                solution(INPUT, marker_size)
+            );
+        }
+    }
+
+    #[test]
+    fn test_marker_stream_chunks() {
+        for chunk_size in 1..=16 {
+            let markers: Vec<Marker> = MarkerStream::new(INPUT.as_bytes(), &[14, 4], chunk_size)
+                .unwrap()
+                .collect::<io::Result<_>>()
+                .unwrap();
+            assert_eq!(
+                markers,
+                vec![
+                    Marker {
+                        size: 4,
+                        offset: 1702
+                    },
+                    Marker {
+                        size: 14,
+                        offset: 3559
+                    },
+                ]
+            );
+        }
+        let mut stream = MarkerStream::new("aaaa".as_bytes(), &[4], 3).unwrap();
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_marker_stream_endless() {
+        let signal = io::repeat(b'a')
+            .take(100_000)
+            .chain("abcdefghijklmn".as_bytes())
+            .chain(io::repeat(b'z'));
+        let mut stream = MarkerStream::new(signal, &[4, 14], 4096).unwrap();
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            Marker {
+                size: 4,
+                offset: 100_004
+            }
+        );
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            Marker {
+                size: 14,
+                offset: 100_014
+            }
+        );
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_signal_profile_example() {
+        let profile = SignalProfile::new(EXAMPLE).unwrap();
+        assert_eq!(
+            profile.markers(14).collect::<Vec<usize>>(),
+            vec![19, 25, 26, 27, 28, 29, 30]
+        );
+        assert_eq!(profile.markers(4).next(), Some(7));
+        assert_eq!(profile.markers(4).count(), 24);
+        assert_eq!(profile.markers(0).count(), 0);
+        assert_eq!(profile.longest_run(), Some((12, 18)));
+        assert_eq!(profile.smallest_missing_marker(), 19);
+        assert_eq!(SignalProfile::new("").unwrap().longest_run(), None);
+        assert_eq!(SignalProfile::new("").unwrap().smallest_missing_marker(), 1);
+        assert!(matches!(
+            SignalProfile::new("ab\u{e9}"),
+            Err(MarkerError::NonAscii(2))
+        ));
+    }
+
+    #[test]
+    fn test_signal_profile_solution() {
+        let profile = SignalProfile::new(INPUT).unwrap();
+        let (start, len) = profile.longest_run().unwrap();
+        let run: HashSet<u8> = INPUT[start..start + len].bytes().collect();
+        assert_eq!(run.len(), len);
+        let missing = profile.smallest_missing_marker();
+        assert_eq!(
+            solution(INPUT, missing),
+            Err(MarkerError::NotFound(missing))
+        );
+        assert_eq!(solution(INPUT, missing - 1), Ok(start + len));
+        for marker_size in [4, 14] {
+            let markers: Vec<usize> = profile.markers(marker_size).collect();
+            assert_eq!(Ok(markers[0]), solution(INPUT, marker_size));
+            for end in markers {
+                let window: HashSet<u8> = INPUT[end - marker_size..end].bytes().collect();
+                assert_eq!(window.len(), marker_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_signal_generator() {
+        let mut generator = SignalGenerator::new("abc", 7).unwrap();
+        assert_eq!(generator.planted(0, 3, 3), Err(PlantError::ZeroSize));
+        assert_eq!(
+            generator.planted(4, 4, 4),
+            Err(PlantError::AlphabetTooSmall(3, 4))
+        );
+        assert_eq!(
+            generator.planted(3, 2, 4),
+            Err(PlantError::PositionTooEarly(2, 3))
+        );
+        assert_eq!(
+            generator.planted(3, 5, 4),
+            Err(PlantError::PositionBeyondSignal(5, 4))
+        );
+        assert_eq!(
+            generator.planted(1, 2, 4),
+            Err(PlantError::UnavoidableMarker(2))
+        );
+        let signal = generator.planted(1, 1, 10).unwrap();
+        assert_eq!(signal.len(), 10);
+        assert_eq!(find_marker(&signal, 1), Ok(1));
+        let signal = SignalGenerator::new("abc", 7)
+            .unwrap()
+            .planted(3, 50, 60)
+            .unwrap();
+        assert_eq!(find_marker(&signal, 3), Ok(50));
+        assert_eq!(
+            SignalGenerator::new("abc", 7).unwrap().planted(3, 50, 60),
+            Ok(signal)
+        );
+        assert_eq!(
+            SignalGenerator::new("aab", 1).unwrap().planted(3, 3, 3),
+            Err(PlantError::AlphabetTooSmall(2, 3))
+        );
+        let signal = SignalGenerator::new("abab", 1)
+            .unwrap()
+            .planted(2, 20, 20)
+            .unwrap();
+        assert_eq!(find_marker(&signal, 2), Ok(20));
+        assert!(matches!(
+            SignalGenerator::new("ab\u{e9}", 1),
+            Err(PlantError::NonAsciiAlphabet('\u{e9}'))
+        ));
+    }
+
+    fn planted_signal() -> impl Strategy<Value = (String, usize, usize)> {
+        (2usize..=14)
+            .prop_flat_map(|size| {
+                (
+                    Just(size),
+                    size..=26,
+                    size..500usize,
+                    0usize..50,
+                    any::<u64>(),
+                )
+            })
+            .prop_map(|(size, alphabet, position, tail, seed)| {
+                let alphabet: String = ('a'..='z').take(alphabet).collect();
+                let signal = SignalGenerator::new(&alphabet, seed)
+                    .unwrap()
+                    .planted(size, position, position + tail)
+                    .unwrap();
+                (signal, size, position)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_detectors_find_planted_marker((signal, size, position) in planted_signal()) {
+            prop_assert_eq!(solution(&signal, size), Ok(position));
+            prop_assert_eq!(find_marker(&signal, size), Ok(position));
+            let profile = SignalProfile::new(&signal).unwrap();
+            prop_assert_eq!(profile.markers(size).next(), Some(position));
+        }
+
+        #[test]
+        fn prop_stream_finds_planted_marker(
+            (signal, size, position) in planted_signal(),
+            chunk_size in 1usize..64,
+        ) {
+            let markers: Vec<Marker> = MarkerStream::new(signal.as_bytes(), &[size], chunk_size)
+                .unwrap()
+                .collect::<io::Result<_>>()
+                .unwrap();
+            prop_assert_eq!(markers, vec![Marker { size, offset: position }]);
+        }
+    }
+
+    #[test]
+    fn test_frames() {
+        let frames: Vec<Frame> = Frames::new("aaaabcdaaaaefghaa", 4).unwrap().collect();
+        assert_eq!(
+            frames,
+            vec![
+                Frame {
+                    start: 7,
+                    payload: "aaa"
+                },
+                Frame {
+                    start: 14,
+                    payload: "haa"
+                },
+            ]
+        );
+        assert_eq!(
+            framing_stats(&frames),
+            Some(FramingStats {
+                frames: 2,
+                average_distance: Some(7.0),
+                average_payload: 3.0,
+            })
+        );
+        assert_eq!(Frames::new("aaaa", 4).unwrap().next(), None);
+        assert!(matches!(
+            Frames::new("a\u{e9}b", 2),
+            Err(MarkerError::NonAscii(1))
+        ));
+        assert!(matches!(Frames::new("abcd", 0), Err(MarkerError::ZeroSize)));
+        assert!(matches!(
+            Frames::new("abcd", 5),
+            Err(MarkerError::SizeExceedsSignal(5, 4))
+        ));
+        assert_eq!(framing_stats(&[]), None);
+    }
+
+    #[test]
+    fn test_frames_solution() {
+        for marker_size in [4, 14] {
+            let frames: Vec<Frame> = Frames::new(INPUT, marker_size).unwrap().collect();
+            assert_eq!(Ok(frames[0].start), find_marker(INPUT, marker_size));
+            for pair in frames.windows(2) {
+                let between = &INPUT[pair[0].start..pair[1].start];
+                assert_eq!(find_marker(between, marker_size), Ok(between.len()));
+                assert_eq!(pair[0].payload, &between[..between.len() - marker_size]);
+            }
+            let last = frames.last().unwrap();
+            assert_eq!(&INPUT[last.start..], last.payload);
+        }
+        let stats = framing_stats(&Frames::new(INPUT, 4).unwrap().collect::<Vec<Frame>>()).unwrap();
+        assert!(stats.frames > 1);
+        assert!(stats.average_distance.unwrap() >= 4.0);
+        let stats =
+            framing_stats(&Frames::new(INPUT, 14).unwrap().collect::<Vec<Frame>>()).unwrap();
+        assert_eq!(stats.frames, 1);
+        assert_eq!(stats.average_distance, None);
+    }
+
+    // Compares both detectors on a long signal with the marker at the very end:
+    //
+    //   cargo test --release day06::tests::bench_find_marker -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_find_marker() {
+        let mut signal = "abcdefghijklm".repeat(100_000);
+        signal.push_str("nopqrstuvwxyz");
+        for marker_size in [4, 14] {
+            let started = Instant::now();
+            let expected = solution(&signal, marker_size).unwrap();
+            let hash_set = started.elapsed();
+            let started = Instant::now();
+            assert_eq!(find_marker(&signal, marker_size), Ok(expected));
+            let last_seen = started.elapsed();
+            println!(
+                "marker size {:>2}: HashSet per window {:>10.2?}, last seen index {:>10.2?}",
+                marker_size, hash_set, last_seen
+            );
+        }
     }
 
     const EXAMPLE: &str = include_str!("example.txt");
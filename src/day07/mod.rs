@@ -1,19 +1,1107 @@
 #[cfg(test)]
 mod tests {
-    fn solution(input: &str) -> usize {
-        input.len()
+    use std::cell::Cell;
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt;
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    const DISK_SIZE: u64 = 70_000_000;
+    const UPDATE_SIZE: u64 = 30_000_000;
+    const SMALL_DIR_SIZE: u64 = 100_000;
+
+    #[derive(Debug, PartialEq)]
+    enum TranscriptLine {
+        Cd(String),
+        Ls,
+        Dir(String),
+        File(u64, String),
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum ParseLineError {
+        UnknownCommand(String),
+        InvalidSize(String),
+        IncorrectFormat(String),
+        PathInName(String),
+    }
+
+    impl Display for ParseLineError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParseLineError::UnknownCommand(line) => {
+                    write!(f, "Unknown command {:?}, expected cd or ls", line)
+                }
+                ParseLineError::InvalidSize(line) => {
+                    write!(f, "Invalid file size in {:?}", line)
+                }
+                ParseLineError::IncorrectFormat(line) => write!(
+                    f,
+                    "Invalid listing entry {:?}, expected \"dir <name>\" or \"<size> <name>\"",
+                    line
+                ),
+                ParseLineError::PathInName(line) => write!(
+                    f,
+                    "Invalid name in {:?}, expected a single name without '/'",
+                    line
+                ),
+            }
+        }
+    }
+
+    impl FromStr for TranscriptLine {
+        type Err = ParseLineError;
+
+        // Names are single path components: `cd` only moves one level at a time, or back
+        // to the root with `cd /`.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let line = if let Some(command) = s.strip_prefix("$ ") {
+                match command.split_once(' ') {
+                    Some(("cd", "/")) => return Ok(TranscriptLine::Cd("/".to_string())),
+                    Some(("cd", dir)) if !dir.is_empty() => TranscriptLine::Cd(dir.to_string()),
+                    None if command == "ls" => TranscriptLine::Ls,
+                    _ => return Err(ParseLineError::UnknownCommand(s.to_string())),
+                }
+            } else {
+                match s.split_once(' ') {
+                    Some(("dir", name)) if !name.is_empty() => {
+                        TranscriptLine::Dir(name.to_string())
+                    }
+                    Some((size, name)) if !name.is_empty() => u64::from_str(size)
+                        .map(|size| TranscriptLine::File(size, name.to_string()))
+                        .map_err(|_| ParseLineError::InvalidSize(s.to_string()))?,
+                    _ => return Err(ParseLineError::IncorrectFormat(s.to_string())),
+                }
+            };
+            match &line {
+                TranscriptLine::Cd(name)
+                | TranscriptLine::Dir(name)
+                | TranscriptLine::File(_, name)
+                    if name.contains('/') =>
+                {
+                    Err(ParseLineError::PathInName(s.to_string()))
+                }
+                _ => Ok(line),
+            }
+        }
+    }
+
+    type NodeId = usize;
+
+    const ROOT: NodeId = 0;
+
+    #[derive(Debug, PartialEq)]
+    enum TreeError {
+        NotADirectory(String),
+        NotAFile(String),
+    }
+
+    impl Display for TreeError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TreeError::NotADirectory(path) => write!(f, "{} is not a directory", path),
+                TreeError::NotAFile(path) => write!(f, "{} is a directory, not a file", path),
+            }
+        }
+    }
+
+    // A directory's entries, by name for lookups and in the order they were added, which
+    // is the order the transcript first mentioned them.
+    #[derive(Debug, Default)]
+    struct Children {
+        by_name: HashMap<String, NodeId>,
+        order: Vec<NodeId>,
+    }
+
+    #[derive(Debug)]
+    enum NodeKind {
+        Dir(Children),
+        File(u64),
+    }
+
+    #[derive(Debug)]
+    struct Node {
+        name: String,
+        parent: Option<NodeId>,
+        kind: NodeKind,
+        // Recursive size, filled on demand and cleared along the parent links whenever
+        // something below changes.
+        size: Cell<Option<u64>>,
+    }
+
+    // Every node lives in one arena and refers to others by index, so walking up and down
+    // the tree never needs more than a shared borrow of it.
+    #[derive(Debug)]
+    struct FsTree {
+        nodes: Vec<Node>,
+    }
+
+    impl FsTree {
+        fn new() -> Self {
+            FsTree {
+                nodes: vec![Node {
+                    name: String::new(),
+                    parent: None,
+                    kind: NodeKind::Dir(Children::default()),
+                    size: Cell::new(None),
+                }],
+            }
+        }
+
+        fn name(&self, id: NodeId) -> &str {
+            &self.nodes[id].name
+        }
+
+        fn parent(&self, id: NodeId) -> Option<NodeId> {
+            self.nodes[id].parent
+        }
+
+        fn depth(&self, id: NodeId) -> usize {
+            std::iter::successors(self.parent(id), |&id| self.parent(id)).count()
+        }
+
+        fn is_dir(&self, id: NodeId) -> bool {
+            matches!(self.nodes[id].kind, NodeKind::Dir(_))
+        }
+
+        fn children(&self, id: NodeId) -> impl DoubleEndedIterator<Item = NodeId> + '_ {
+            let children: &[NodeId] = match &self.nodes[id].kind {
+                NodeKind::Dir(children) => &children.order,
+                NodeKind::File(_) => &[],
+            };
+            children.iter().copied()
+        }
+
+        fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+            match &self.nodes[id].kind {
+                NodeKind::Dir(children) => children.by_name.get(name).copied(),
+                NodeKind::File(_) => None,
+            }
+        }
+
+        fn path(&self, id: NodeId) -> String {
+            let mut names = Vec::new();
+            let mut node = Some(id);
+            while let Some(id) = node.filter(|&id| id != ROOT) {
+                names.push(self.name(id));
+                node = self.parent(id);
+            }
+            names.reverse();
+            format!("/{}", names.join("/"))
+        }
+
+        // Looks up an absolute path such as `/a/e`. Empty components are skipped, so `/`
+        // is the root and a trailing slash is allowed.
+        fn lookup(&self, path: &str) -> Option<NodeId> {
+            path.strip_prefix('/')?
+                .split('/')
+                .filter(|name| !name.is_empty())
+                .try_fold(ROOT, |id, name| self.child(id, name))
+        }
+
+        // Adds an empty directory, or returns the existing one with that name.
+        fn add_dir(&mut self, parent: NodeId, name: &str) -> Result<NodeId, TreeError> {
+            match self.insert(parent, name, NodeKind::Dir(Children::default()))? {
+                id if self.is_dir(id) => Ok(id),
+                id => Err(TreeError::NotADirectory(self.path(id))),
+            }
+        }
+
+        // Adds a file, or updates the size of the existing one with that name.
+        fn add_file(&mut self, parent: NodeId, name: &str, size: u64) -> Result<NodeId, TreeError> {
+            let id = self.insert(parent, name, NodeKind::File(size))?;
+            match &mut self.nodes[id].kind {
+                NodeKind::File(old) => *old = size,
+                NodeKind::Dir(_) => return Err(TreeError::NotAFile(self.path(id))),
+            }
+            self.invalidate(id);
+            Ok(id)
+        }
+
+        // Detaches a child from its directory. The node stays in the arena, but nothing
+        // reachable from the root refers to it any more.
+        fn remove(&mut self, parent: NodeId, name: &str) -> Option<NodeId> {
+            let id = match &mut self.nodes[parent].kind {
+                NodeKind::Dir(children) => {
+                    let id = children.by_name.remove(name)?;
+                    children.order.retain(|&child| child != id);
+                    id
+                }
+                NodeKind::File(_) => return None,
+            };
+            self.invalidate(parent);
+            Some(id)
+        }
+
+        fn insert(
+            &mut self,
+            parent: NodeId,
+            name: &str,
+            kind: NodeKind,
+        ) -> Result<NodeId, TreeError> {
+            let next = self.nodes.len();
+            let id = match &mut self.nodes[parent].kind {
+                NodeKind::Dir(children) => {
+                    let id = *children.by_name.entry(name.to_string()).or_insert(next);
+                    if id == next {
+                        children.order.push(id);
+                    }
+                    id
+                }
+                NodeKind::File(_) => return Err(TreeError::NotADirectory(self.path(parent))),
+            };
+            if id == next {
+                self.nodes.push(Node {
+                    name: name.to_string(),
+                    parent: Some(parent),
+                    kind,
+                    size: Cell::new(None),
+                });
+                self.invalidate(parent);
+            }
+            Ok(id)
+        }
+
+        fn invalidate(&self, id: NodeId) {
+            let mut node = Some(id);
+            while let Some(id) = node {
+                self.nodes[id].size.set(None);
+                node = self.parent(id);
+            }
+        }
+
+        // Fills the missing sizes bottom-up, so every child is known before its parent.
+        // Subtrees whose size is already cached are not descended into.
+        fn size(&self, id: NodeId) -> u64 {
+            let mut stack = vec![(id, false)];
+            while let Some((node, expanded)) = stack.pop() {
+                if self.nodes[node].size.get().is_some() {
+                    continue;
+                }
+                match &self.nodes[node].kind {
+                    NodeKind::File(size) => self.nodes[node].size.set(Some(*size)),
+                    NodeKind::Dir(children) if expanded => {
+                        let size = children
+                            .order
+                            .iter()
+                            .map(|&child| self.nodes[child].size.get().unwrap_or(0))
+                            .sum();
+                        self.nodes[node].size.set(Some(size));
+                    }
+                    NodeKind::Dir(_) => {
+                        stack.push((node, true));
+                        stack.extend(self.children(node).map(|child| (child, false)));
+                    }
+                }
+            }
+            self.nodes[id].size.get().unwrap_or(0)
+        }
+
+        fn pre_order(&self, id: NodeId) -> PreOrder<'_> {
+            PreOrder {
+                tree: self,
+                stack: vec![id],
+            }
+        }
+
+        fn post_order(&self, id: NodeId) -> PostOrder<'_> {
+            PostOrder {
+                tree: self,
+                stack: vec![(id, false)],
+            }
+        }
+
+        fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+            self.pre_order(ROOT).filter(|&id| self.is_dir(id))
+        }
+    }
+
+    // Parents before children, siblings in transcript order.
+    struct PreOrder<'a> {
+        tree: &'a FsTree,
+        stack: Vec<NodeId>,
+    }
+
+    impl Iterator for PreOrder<'_> {
+        type Item = NodeId;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let id = self.stack.pop()?;
+            self.stack.extend(self.tree.children(id).rev());
+            Some(id)
+        }
+    }
+
+    // Children before parents, siblings in transcript order. A node is yielded the second time
+    // it comes off the stack, once everything below it has been.
+    struct PostOrder<'a> {
+        tree: &'a FsTree,
+        stack: Vec<(NodeId, bool)>,
+    }
+
+    impl Iterator for PostOrder<'_> {
+        type Item = NodeId;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                let (id, expanded) = self.stack.pop()?;
+                if expanded {
+                    return Some(id);
+                }
+                self.stack.push((id, true));
+                self.stack
+                    .extend(self.tree.children(id).rev().map(|child| (child, false)));
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum TranscriptError {
+        InvalidLine(ParseLineError),
+        InvalidTree(TreeError),
+        Inconsistent(Inconsistency),
+    }
+
+    impl Display for TranscriptError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TranscriptError::InvalidLine(err) => write!(f, "{}", err),
+                TranscriptError::InvalidTree(err) => write!(f, "{}", err),
+                TranscriptError::Inconsistent(err) => write!(f, "{}", err),
+            }
+        }
+    }
+
+    impl From<ParseLineError> for TranscriptError {
+        fn from(err: ParseLineError) -> Self {
+            TranscriptError::InvalidLine(err)
+        }
+    }
+
+    impl From<TreeError> for TranscriptError {
+        fn from(err: TreeError) -> Self {
+            TranscriptError::InvalidTree(err)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ParseError {
+        line: usize,
+        error: TranscriptError,
+    }
+
+    impl Display for ParseError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Line {}: {}", self.line, self.error)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Inconsistency {
+        Relisted(String, usize),
+        UnlistedDir(String),
+        AboveRoot,
+    }
+
+    impl Display for Inconsistency {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Inconsistency::Relisted(path, previous) => write!(
+                    f,
+                    "{} was listed on line {} with different contents",
+                    path, previous
+                ),
+                Inconsistency::UnlistedDir(path) => {
+                    write!(f, "Changed into {}, which no listing mentions", path)
+                }
+                Inconsistency::AboveRoot => write!(f, "Changed to the parent of the root"),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Issue {
+        line: usize,
+        inconsistency: Inconsistency,
+    }
+
+    impl Display for Issue {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Line {}: {}", self.line, self.inconsistency)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Policy {
+        // The first inconsistency fails the whole transcript.
+        Strict,
+        // Inconsistencies are collected and the latest listing of a directory replaces
+        // whatever was known about it before.
+        Merge,
+    }
+
+    // Listing entries by name, with sizes for files and `None` for directories.
+    type Contents = BTreeMap<String, Option<u64>>;
+
+    struct Replay {
+        policy: Policy,
+        tree: FsTree,
+        cwd: NodeId,
+        // The latest complete listing of every directory and the line of its `ls`.
+        listings: HashMap<NodeId, (usize, Contents)>,
+        listing: Option<(NodeId, usize, Contents)>,
+        issues: Vec<Issue>,
+    }
+
+    impl Replay {
+        fn new(policy: Policy) -> Self {
+            Replay {
+                policy,
+                tree: FsTree::new(),
+                cwd: ROOT,
+                listings: HashMap::new(),
+                listing: None,
+                issues: Vec::new(),
+            }
+        }
+
+        fn report(&mut self, line: usize, inconsistency: Inconsistency) -> Result<(), ParseError> {
+            match self.policy {
+                Policy::Strict => Err(ParseError {
+                    line,
+                    error: TranscriptError::Inconsistent(inconsistency),
+                }),
+                Policy::Merge => {
+                    self.issues.push(Issue {
+                        line,
+                        inconsistency,
+                    });
+                    Ok(())
+                }
+            }
+        }
+
+        fn apply(&mut self, number: usize, line: &str) -> Result<(), ParseError> {
+            let at = |error: TranscriptError| ParseError {
+                line: number,
+                error,
+            };
+            let line = TranscriptLine::from_str(line).map_err(|err| at(err.into()))?;
+            if matches!(line, TranscriptLine::Cd(_) | TranscriptLine::Ls) {
+                self.close_listing()?;
+            }
+            match line {
+                TranscriptLine::Cd(dir) if dir == "/" => self.cwd = ROOT,
+                TranscriptLine::Cd(dir) if dir == ".." => match self.tree.parent(self.cwd) {
+                    Some(parent) => self.cwd = parent,
+                    None => self.report(number, Inconsistency::AboveRoot)?,
+                },
+                TranscriptLine::Cd(dir) => {
+                    let listed = self
+                        .listings
+                        .get(&self.cwd)
+                        .is_some_and(|(_, contents)| contents.get(&dir) == Some(&None));
+                    self.cwd = self
+                        .tree
+                        .add_dir(self.cwd, &dir)
+                        .map_err(|err| at(err.into()))?;
+                    if !listed {
+                        let path = self.tree.path(self.cwd);
+                        self.report(number, Inconsistency::UnlistedDir(path))?;
+                    }
+                }
+                TranscriptLine::Ls => self.listing = Some((self.cwd, number, Contents::new())),
+                TranscriptLine::Dir(name) => {
+                    self.replace_changed(&name, true);
+                    self.tree
+                        .add_dir(self.cwd, &name)
+                        .map_err(|err| at(err.into()))?;
+                    if let Some((_, _, contents)) = &mut self.listing {
+                        contents.insert(name, None);
+                    }
+                }
+                TranscriptLine::File(size, name) => {
+                    self.replace_changed(&name, false);
+                    self.tree
+                        .add_file(self.cwd, &name, size)
+                        .map_err(|err| at(err.into()))?;
+                    if let Some((_, _, contents)) = &mut self.listing {
+                        contents.insert(name, Some(size));
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        // A listing shows the directory as it is now, so the first mention of an entry in it
+        // replaces a node of the other kind, and closing the listing reports the change.
+        // The same name listed twice with different kinds is still an error.
+        fn replace_changed(&mut self, name: &str, is_dir: bool) {
+            let first_mention = self
+                .listing
+                .as_ref()
+                .is_some_and(|(_, _, contents)| !contents.contains_key(name));
+            let changed = self
+                .tree
+                .child(self.cwd, name)
+                .is_some_and(|child| self.tree.is_dir(child) != is_dir);
+            if first_mention && changed {
+                self.tree.remove(self.cwd, name);
+            }
+        }
+
+        // A listing ends at the next command. Anything the directory held that the listing
+        // does not mention is dropped, so the latest listing always wins.
+        fn close_listing(&mut self) -> Result<(), ParseError> {
+            let Some((dir, line, contents)) = self.listing.take() else {
+                return Ok(());
+            };
+            if let Some((previous, listed)) = self.listings.get(&dir) {
+                if *listed != contents {
+                    let inconsistency = Inconsistency::Relisted(self.tree.path(dir), *previous);
+                    self.report(line, inconsistency)?;
+                }
+            }
+            let stale: Vec<String> = self
+                .tree
+                .children(dir)
+                .map(|child| self.tree.name(child).to_string())
+                .filter(|name| !contents.contains_key(name))
+                .collect();
+            for name in stale {
+                self.tree.remove(dir, &name);
+            }
+            self.listings.insert(dir, (line, contents));
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct Report {
+        tree: FsTree,
+        issues: Vec<Issue>,
+    }
+
+    // Replays the transcript from the root, checking it against itself along the way.
+    // Directories are created as soon as they are mentioned, either by a listing or by
+    // changing into them, and `cd ..` at the root stays there like a shell does.
+    fn check_transcript(input: &str, policy: Policy) -> Result<Report, ParseError> {
+        let mut replay = Replay::new(policy);
+        for (i, line) in input.lines().enumerate() {
+            replay.apply(i + 1, line)?;
+        }
+        replay.close_listing()?;
+        Ok(Report {
+            tree: replay.tree,
+            issues: replay.issues,
+        })
+    }
+
+    fn parse_input(input: &str) -> Result<FsTree, ParseError> {
+        check_transcript(input, Policy::Merge).map(|report| report.tree)
+    }
+
+    fn solution_part1(input: &str) -> Result<u64, ParseError> {
+        let tree = parse_input(input)?;
+        Ok(tree
+            .dirs()
+            .map(|id| tree.size(id))
+            .filter(|&size| size <= SMALL_DIR_SIZE)
+            .sum())
+    }
+
+    // The root is always big enough to free the space when anything is needed at all, so
+    // there is always a candidate.
+    fn solution_part2(input: &str) -> Result<u64, ParseError> {
+        let tree = parse_input(input)?;
+        let needed = (UPDATE_SIZE + tree.size(ROOT)).saturating_sub(DISK_SIZE);
+        Ok(tree
+            .dirs()
+            .map(|id| tree.size(id))
+            .filter(|&size| size >= needed)
+            .min()
+            .unwrap_or(0))
+    }
+
+    // Shows a subtree the way the puzzle describes the example filesystem.
+    struct Listing<'a> {
+        tree: &'a FsTree,
+        root: NodeId,
+    }
+
+    impl Display for Listing<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let base = self.tree.depth(self.root);
+            for id in self.tree.pre_order(self.root) {
+                let indent = "  ".repeat(self.tree.depth(id) - base);
+                let name = if id == ROOT { "/" } else { self.tree.name(id) };
+                match self.tree.nodes[id].kind {
+                    NodeKind::Dir(_) => writeln!(f, "{}- {} (dir)", indent, name)?,
+                    NodeKind::File(size) => {
+                        writeln!(f, "{}- {} (file, size={})", indent, name, size)?
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Listing<'_> {
+        fn to_json(&self) -> String {
+            let mut json = String::new();
+            self.write_json(self.root, &mut json);
+            json
+        }
+
+        fn write_json(&self, id: NodeId, json: &mut String) {
+            let name = if id == ROOT { "/" } else { self.tree.name(id) };
+            match self.tree.nodes[id].kind {
+                NodeKind::File(size) => json.push_str(&format!(
+                    "{{\"name\":{},\"type\":\"file\",\"size\":{}}}",
+                    json_string(name),
+                    size
+                )),
+                NodeKind::Dir(_) => {
+                    json.push_str(&format!(
+                        "{{\"name\":{},\"type\":\"dir\",\"size\":{},\"children\":[",
+                        json_string(name),
+                        self.tree.size(id)
+                    ));
+                    for (i, child) in self.tree.children(id).enumerate() {
+                        if i > 0 {
+                            json.push(',');
+                        }
+                        self.write_json(child, json);
+                    }
+                    json.push_str("]}");
+                }
+            }
+        }
+    }
+
+    fn json_string(s: &str) -> String {
+        let mut json = String::from('"');
+        for c in s.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+                c => json.push(c),
+            }
+        }
+        json.push('"');
+        json
+    }
+
+    // Rounds up to one decimal below 10 and to whole units above, like `du -h`. A size
+    // that rounds up to 1024 of a unit moves on to the next one.
+    fn human_size(bytes: u64) -> String {
+        if bytes < 1024 {
+            return bytes.to_string();
+        }
+        let mut size = bytes as f64;
+        for unit in ["K", "M", "G", "T", "P", "E"] {
+            size /= 1024.0;
+            let tenths = (size * 10.0).ceil() / 10.0;
+            if tenths < 10.0 {
+                return format!("{:.1}{}", tenths, unit);
+            }
+            if size.ceil() < 1024.0 || unit == "E" {
+                return format!("{}{}", size.ceil(), unit);
+            }
+        }
+        unreachable!()
+    }
+
+    const BAR_WIDTH: usize = 20;
+
+    #[derive(Debug, PartialEq)]
+    struct DuEntry {
+        path: String,
+        size: u64,
+    }
+
+    // Directory sizes below a root, largest first, as `du -h -d <depth> | sort -rh` would
+    // list them. Percentages are relative to the root.
+    struct DiskUsage {
+        total: u64,
+        entries: Vec<DuEntry>,
+    }
+
+    impl DiskUsage {
+        fn new(tree: &FsTree, root: NodeId, max_depth: Option<usize>) -> Self {
+            let base = tree.depth(root);
+            let mut entries: Vec<DuEntry> = tree
+                .pre_order(root)
+                .filter(|&id| tree.is_dir(id))
+                .filter(|&id| max_depth.is_none_or(|depth| tree.depth(id) - base <= depth))
+                .map(|id| DuEntry {
+                    path: tree.path(id),
+                    size: tree.size(id),
+                })
+                .collect();
+            entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+            DiskUsage {
+                total: tree.size(root),
+                entries,
+            }
+        }
+
+        fn percent(&self, entry: &DuEntry) -> f64 {
+            if self.total == 0 {
+                0.0
+            } else {
+                entry.size as f64 * 100.0 / self.total as f64
+            }
+        }
+
+        fn to_json(&self) -> String {
+            let entries: Vec<String> = self
+                .entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "{{\"path\":{},\"size\":{},\"percent\":{:.1}}}",
+                        json_string(&entry.path),
+                        entry.size,
+                        self.percent(entry)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"total\":{},\"entries\":[{}]}}",
+                self.total,
+                entries.join(",")
+            )
+        }
+    }
+
+    impl Display for DiskUsage {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for entry in &self.entries {
+                let percent = self.percent(entry);
+                let filled = (percent * BAR_WIDTH as f64 / 100.0).round() as usize;
+                writeln!(
+                    f,
+                    "{:>5}  [{:<width$}] {:>5.1}%  {}",
+                    human_size(entry.size),
+                    "#".repeat(filled),
+                    percent,
+                    entry.path,
+                    width = BAR_WIDTH
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            TranscriptLine::from_str("$ cd /"),
+            Ok(TranscriptLine::Cd("/".to_string()))
+        );
+        assert_eq!(TranscriptLine::from_str("$ ls"), Ok(TranscriptLine::Ls));
+        assert_eq!(
+            TranscriptLine::from_str("dir a"),
+            Ok(TranscriptLine::Dir("a".to_string()))
+        );
+        assert_eq!(
+            TranscriptLine::from_str("14848514 b.txt"),
+            Ok(TranscriptLine::File(14848514, "b.txt".to_string()))
+        );
+        assert_eq!(
+            TranscriptLine::from_str("$ rm -rf /"),
+            Err(ParseLineError::UnknownCommand("$ rm -rf /".to_string()))
+        );
+        assert_eq!(
+            TranscriptLine::from_str("12k b.txt"),
+            Err(ParseLineError::InvalidSize("12k b.txt".to_string()))
+        );
+        assert_eq!(
+            TranscriptLine::from_str("b.txt"),
+            Err(ParseLineError::IncorrectFormat("b.txt".to_string()))
+        );
+        for line in ["$ cd /a/b", "$ cd a/", "dir a/b", "10 /b.txt"] {
+            assert_eq!(
+                TranscriptLine::from_str(line),
+                Err(ParseLineError::PathInName(line.to_string()))
+            );
+        }
+        assert_eq!(
+            parse_input("$ cd /\n$ cd /a/b").unwrap_err().to_string(),
+            "Line 2: Invalid name in \"$ cd /a/b\", expected a single name without '/'"
+        );
+        assert_eq!(
+            parse_input("$ cd /\n$ ls\nfoo").unwrap_err(),
+            ParseError {
+                line: 3,
+                error: TranscriptError::InvalidLine(ParseLineError::IncorrectFormat(
+                    "foo".to_string()
+                ))
+            }
+        );
+        assert_eq!(
+            parse_input("$ ls\n10 f\n$ cd f").unwrap_err(),
+            ParseError {
+                line: 3,
+                error: TranscriptError::InvalidTree(TreeError::NotADirectory("/f".to_string()))
+            }
+        );
+        assert_eq!(
+            parse_input("$ ls\ndir a\n10 a").unwrap_err(),
+            ParseError {
+                line: 3,
+                error: TranscriptError::InvalidTree(TreeError::NotAFile("/a".to_string()))
+            }
+        );
     }
 
     #[test]
-    fn test_example() {
-        assert_eq!(solution(EXAMPLE), 0);
+    fn test_parse_input() {
+        let tree = parse_input(EXAMPLE).unwrap();
+        let size = |path| tree.size(tree.lookup(path).unwrap());
+        assert_eq!(size("/"), 48381165);
+        assert_eq!(size("/a"), 94853);
+        assert_eq!(size("/a/e"), 584);
+        assert_eq!(size("/a/e/i"), 584);
+        assert_eq!(size("/d/"), 24933642);
+        assert_eq!(tree.lookup("/a/x"), None);
+        assert_eq!(tree.lookup("a"), None);
+        let tree = parse_input("$ cd ..\n$ cd x\n$ ls\n10 y").unwrap();
+        assert_eq!(tree.lookup("/x/y").map(|id| tree.size(id)), Some(10));
     }
 
     #[test]
-    fn test_solution() {
-        assert_eq!(solution(INPUT), 0);
+    fn test_tree_navigation() {
+        let tree = parse_input(EXAMPLE).unwrap();
+        let a = tree.lookup("/a").unwrap();
+        let e = tree.lookup("/a/e").unwrap();
+        assert_eq!(tree.path(e), "/a/e");
+        assert_eq!(tree.path(ROOT), "/");
+        assert_eq!(tree.parent(e), Some(a));
+        assert_eq!(tree.parent(ROOT), None);
+        assert!(tree.is_dir(e));
+        assert!(!tree.is_dir(tree.lookup("/b.txt").unwrap()));
+        let paths =
+            |ids: Vec<NodeId>| -> Vec<String> { ids.into_iter().map(|id| tree.path(id)).collect() };
+        assert_eq!(
+            paths(tree.pre_order(a).collect()),
+            vec!["/a", "/a/e", "/a/e/i", "/a/f", "/a/g", "/a/h.lst"]
+        );
+        assert_eq!(
+            paths(tree.post_order(a).collect()),
+            vec!["/a/e/i", "/a/e", "/a/f", "/a/g", "/a/h.lst", "/a"]
+        );
+        assert_eq!(paths(tree.dirs().collect()), vec!["/", "/a", "/a/e", "/d"]);
+        assert_eq!(tree.post_order(ROOT).last(), Some(ROOT));
+        assert_eq!(tree.pre_order(ROOT).count(), tree.post_order(ROOT).count());
     }
 
+    #[test]
+    fn test_size_cache() {
+        let mut tree = parse_input(EXAMPLE).unwrap();
+        let d = tree.lookup("/d").unwrap();
+        let e = tree.lookup("/a/e").unwrap();
+        assert_eq!(tree.size(ROOT), 48381165);
+        assert_eq!(tree.nodes[e].size.get(), Some(584));
+        let file = tree.add_file(e, "j", 16).unwrap();
+        assert_eq!(tree.nodes[e].size.get(), None);
+        assert_eq!(tree.nodes[ROOT].size.get(), None);
+        assert_eq!(tree.nodes[d].size.get(), Some(24933642));
+        assert_eq!(tree.size(e), 600);
+        assert_eq!(tree.size(ROOT), 48381181);
+        assert_eq!(tree.add_file(e, "j", 6), Ok(file));
+        assert_eq!(tree.size(ROOT), 48381171);
+        // A cached subtree is taken as is, without looking at anything below it.
+        tree.nodes[tree.lookup("/d/j").unwrap()].size.set(Some(0));
+        tree.invalidate(e);
+        assert_eq!(tree.size(ROOT), 48381171);
+        assert_eq!(tree.add_dir(ROOT, "d"), Ok(d));
+        assert_eq!(
+            tree.add_dir(file, "k"),
+            Err(TreeError::NotADirectory("/a/e/j".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_consistent_transcript() {
+        for policy in [Policy::Strict, Policy::Merge] {
+            let report = check_transcript(EXAMPLE, policy).unwrap();
+            assert_eq!(report.issues, vec![]);
+            assert_eq!(report.tree.size(ROOT), 48381165);
+        }
+        let relisted = "$ cd /\n$ ls\ndir a\n1 b\n$ cd a\n$ cd ..\n$ ls\ndir a\n1 b";
+        assert!(check_transcript(relisted, Policy::Strict).is_ok());
+    }
+
+    #[test]
+    fn test_inconsistent_transcript() {
+        let transcript = [
+            "$ cd /", "$ ls", "dir a", "10 b", "$ cd a", "$ ls", "5 c", "$ cd ..", "$ cd ..",
+            "$ ls", "dir a", "20 d", "$ cd x", "$ ls", "7 y",
+        ]
+        .join("\n");
+        let report = check_transcript(&transcript, Policy::Merge).unwrap();
+        let issues: Vec<String> = report.issues.iter().map(Issue::to_string).collect();
+        assert_eq!(
+            issues,
+            vec![
+                "Line 9: Changed to the parent of the root",
+                "Line 10: / was listed on line 2 with different contents",
+                "Line 13: Changed into /x, which no listing mentions",
+            ]
+        );
+        let tree = report.tree;
+        assert_eq!(tree.lookup("/b"), None);
+        assert_eq!(tree.lookup("/d").map(|id| tree.size(id)), Some(20));
+        assert_eq!(tree.lookup("/a").map(|id| tree.size(id)), Some(5));
+        assert_eq!(tree.size(ROOT), 32);
+        assert_eq!(
+            check_transcript(&transcript, Policy::Strict).unwrap_err(),
+            ParseError {
+                line: 9,
+                error: TranscriptError::Inconsistent(Inconsistency::AboveRoot)
+            }
+        );
+        let relisted = &transcript.replace("$ cd ..\n$ cd ..", "$ cd ..");
+        assert_eq!(
+            check_transcript(relisted, Policy::Strict)
+                .unwrap_err()
+                .to_string(),
+            "Line 9: / was listed on line 2 with different contents"
+        );
+        for (before, after) in [("dir a", "10 a"), ("10 a", "dir a")] {
+            let changed = format!("$ ls\n{}\n$ ls\n{}", before, after);
+            let report = check_transcript(&changed, Policy::Merge).unwrap();
+            assert_eq!(
+                report.issues,
+                vec![Issue {
+                    line: 3,
+                    inconsistency: Inconsistency::Relisted("/".to_string(), 1)
+                }]
+            );
+            let a = report.tree.lookup("/a").unwrap();
+            assert_eq!(report.tree.is_dir(a), after == "dir a");
+            assert_eq!(report.tree.size(ROOT), if after == "10 a" { 10 } else { 0 });
+            assert_eq!(
+                check_transcript(&changed, Policy::Strict).unwrap_err(),
+                ParseError {
+                    line: 3,
+                    error: TranscriptError::Inconsistent(Inconsistency::Relisted(
+                        "/".to_string(),
+                        1
+                    ))
+                }
+            );
+        }
+        assert_eq!(
+            check_transcript("$ cd /\n$ cd x", Policy::Strict).unwrap_err(),
+            ParseError {
+                line: 2,
+                error: TranscriptError::Inconsistent(Inconsistency::UnlistedDir("/x".to_string()))
+            }
+        );
+    }
+
+    #[test]
+    fn test_listing() {
+        let tree = parse_input(EXAMPLE).unwrap();
+        let listing = Listing {
+            tree: &tree,
+            root: ROOT,
+        };
+        assert_eq!(listing.to_string(), PUZZLE_LISTING);
+        let a = tree.lookup("/a").unwrap();
+        assert_eq!(
+            Listing {
+                tree: &tree,
+                root: a
+            }
+            .to_json(),
+            concat!(
+                r#"{"name":"a","type":"dir","size":94853,"children":["#,
+                r#"{"name":"e","type":"dir","size":584,"children":["#,
+                r#"{"name":"i","type":"file","size":584}]},"#,
+                r#"{"name":"f","type":"file","size":29116},"#,
+                r#"{"name":"g","type":"file","size":2557},"#,
+                r#"{"name":"h.lst","type":"file","size":62596}]}"#
+            )
+        );
+        assert_eq!(json_string("a\"b\\c\n"), r#""a\"b\\c\u000a""#);
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0");
+        assert_eq!(human_size(584), "584");
+        assert_eq!(human_size(1024), "1.0K");
+        assert_eq!(human_size(1025), "1.1K");
+        assert_eq!(human_size(94853), "93K");
+        assert_eq!(human_size(24933642), "24M");
+        assert_eq!(human_size(48381165), "47M");
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(10239), "10K");
+        assert_eq!(human_size(1048575), "1.0M");
+        assert_eq!(human_size(1073741823), "1.0G");
+        assert_eq!(human_size(u64::MAX), "16E");
+    }
+
+    #[test]
+    fn test_disk_usage() {
+        let tree = parse_input(EXAMPLE).unwrap();
+        let du = DiskUsage::new(&tree, ROOT, None);
+        assert_eq!(
+            du.to_string(),
+            concat!(
+                "  47M  [####################] 100.0%  /\n",
+                "  24M  [##########          ]  51.5%  /d\n",
+                "  93K  [                    ]   0.2%  /a\n",
+                "  584  [                    ]   0.0%  /a/e\n",
+            )
+        );
+        let du = DiskUsage::new(&tree, ROOT, Some(1));
+        assert_eq!(
+            du.entries
+                .iter()
+                .map(|entry| entry.path.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["/", "/d", "/a"]
+        );
+        let du = DiskUsage::new(&tree, tree.lookup("/a").unwrap(), Some(0));
+        assert_eq!(
+            du.to_json(),
+            r#"{"total":94853,"entries":[{"path":"/a","size":94853,"percent":100.0}]}"#
+        );
+    }
+
+    #[test]
+    fn test_part1_example() {
+        assert_eq!(solution_part1(EXAMPLE), Ok(95437));
+    }
+
+    #[test]
+    fn test_part2_example() {
+        assert_eq!(solution_part2(EXAMPLE), Ok(24933642));
+    }
+
+    // The example filesystem as drawn in the puzzle.
+    const PUZZLE_LISTING: &str = "\
+- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+";
+
     const EXAMPLE: &str = include_str!("example.txt");
-    const INPUT: &str = include_str!("input.txt");
 }